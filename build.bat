@@ -5,6 +5,8 @@ mkdir dist
 del /f /s /q .\dist\*
 cd .\dist
 mkdir highlighting
+mkdir themes
 cd ..
 copy .\build\release\prettier.exe .\dist
-copy .\highlighting .\dist\highlighting
\ No newline at end of file
+copy .\highlighting .\dist\highlighting
+copy .\themes .\dist\themes
\ No newline at end of file
@@ -3,6 +3,8 @@ rm -rf ./dist
 mkdir -p dist
 cd ./dist
 mkdir highlighting
+mkdir themes
 cd ..
 cp ./build/release/prettier ./dist
-cp -r ./highlighting ./dist/highlighting
\ No newline at end of file
+cp -r ./highlighting ./dist/highlighting
+cp -r ./themes ./dist/themes
\ No newline at end of file
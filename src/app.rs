@@ -1,42 +1,43 @@
 use std::fmt::Display;
 use std::rc::Rc;
+use std::io::{self, BufWriter, IsTerminal, Read, Write};
 use std::{env, collections::HashMap, fs};
 use colored::Colorize;
-use regex::Regex;
+use regex::{bytes, Regex};
 use serde::Deserialize;
+use crate::config::{Config, ConfigLayer, Layer};
+use crate::encoding::Encoding;
+use crate::hexdump::{self, BinaryMode};
 use crate::logger::Logger;
+use crate::terminal::{self, ColorMode, ColorSupport};
 use crate::tokenizer::Tokenizer;
 use crate::highlighting::Highlighter;
 use crate::language_pattern::LangHighlighter;
+use crate::output::{self, Document, LineRanges, OutputFormat, OutputOptions, DEFAULT_TAB_WIDTH};
+use crate::output::ansi::Decorations;
+use crate::output::tokens::{self, Emit};
 
-lazy_static! {
-    static ref SPACE_CHAR: String = String::from(" ");
-}
+static STDIN_PATH: &str = "-";
 
-macro_rules! color_map {
-    ( $( $k: ident : $r: literal $g: literal $b: literal ) , * , ) => {
-        HashMap::from([
-            $ ( 
-                (stringify!($k), ($r as u8, $g as u8, $b as u8)),
-            ) *
-        ])
-    };
-}
-
-macro_rules! colorize {
-    ( $self : expr , $str : expr , $color : expr ) => {
-        {
-            let color = $self.config.color_map.get($color).unwrap();
-            $str.truecolor(color.0, color.1, color.2)
-        }
-    };
+fn load_file(file_path: &String) -> Result<Vec<u8>, String> {
+    if file_path == STDIN_PATH {
+        let mut bytes = Vec::new();
+        return io::stdin().read_to_end(&mut bytes)
+            .map(|_| bytes)
+            .map_err(|e| format!("Stdin IO Error: {}", e))
+    }
+    fs::read(file_path).map_err(|e| format!("File IO Error: {}: {}", file_path, e))
 }
 
-fn load_file(file_path: &String) -> Result<Vec<u8>, String> {
-    if let Ok(bytes) = fs::read(file_path) {
-        Ok(bytes)
-    } else {
-        Err(format!("File IO Error"))
+/// `LF`, `CRLF`, `mixed` or `none`, for the header.
+fn line_endings(bytes: &[u8]) -> &'static str {
+    let lf = bytes.iter().filter(|byte| **byte == b'\n').count();
+    let crlf = bytes.windows(2).filter(|pair| pair == b"\r\n").count();
+    match (lf, crlf) {
+        (0, _) => "none",
+        (lf, crlf) if lf == crlf => "CRLF",
+        (_, 0) => "LF",
+        _ => "mixed",
     }
 }
 
@@ -49,97 +50,258 @@ pub enum LogLevel {
     All = 0,
 }
 
+impl LogLevel {
+    pub fn parse(level: &str) -> Option<LogLevel> {
+        match level {
+            "0" | "all" => Some(LogLevel::All),
+            "1" | "warn" => Some(LogLevel::Warn),
+            "2" | "error" => Some(LogLevel::Error),
+            "3" | "never" => Some(LogLevel::Never),
+            _ => None,
+        }
+    }
+}
+
+impl Display for LogLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogLevel::All => write!(f, "all"),
+            LogLevel::Warn => write!(f, "warn"),
+            LogLevel::Error => write!(f, "error"),
+            LogLevel::Never => write!(f, "never"),
+        }
+    }
+}
+
 pub struct Arguments {
     file_paths: Vec<String>,
-    log_level: LogLevel,
+    log_level: Option<LogLevel>,
+    lang: Option<String>,
+    theme: Option<String>,
+    color_mode: Option<ColorMode>,
+    print_config: bool,
+    output: OutputFormat,
+    output_options: OutputOptions,
+    emit: Option<Emit>,
+    line_ranges: LineRanges,
+    highlights: LineRanges,
+    grep: Option<bytes::Regex>,
+    /// The lines printed before and after a `--grep` match.
+    context: (usize, usize),
+    tab_width: usize,
+    show_whitespace: bool,
+    /// What the files that aren't UTF-8 are read as.
+    encoding: Encoding,
+    binary: BinaryMode,
 }
 
 impl Arguments {
+    /// The flags that override the config files.
+    fn config_layer(&self) -> ConfigLayer {
+        let mut config_layer = ConfigLayer::new(Layer::Cli);
+        config_layer.theme = self.theme.clone();
+        config_layer.color_mode = self.color_mode;
+        config_layer.log_level = self.log_level;
+        config_layer
+    }
     pub fn from_env() -> Result<Arguments, String> {
-        let args: Vec<String> = env::args().collect();
-        let mut log_level = LogLevel::Error;
+        let args = split_args(env::args());
+        let mut log_level = None;
         let mut file_paths = Vec::new();
+        let mut lang = None;
+        let mut theme = None;
+        let mut color_mode = None;
+        let mut print_config = false;
+        let mut output = OutputFormat::Terminal;
+        let mut output_options = OutputOptions::default();
+        let mut emit = None;
+        let mut line_ranges = LineRanges::default();
+        let mut highlights = LineRanges::default();
+        let mut grep = None;
+        let (mut before, mut after) = (None, None);
+        let mut context = 0;
+        let mut wrap = None;
+        let mut decorations = Decorations::default();
+        let mut tab_width = DEFAULT_TAB_WIDTH;
+        let mut show_whitespace = false;
+        let mut encoding = Encoding::Utf8;
+        let mut binary = BinaryMode::Hex;
         let mut curr = 1;
         while curr < args.len() {
             if args[curr].eq("--log-level") {
-                curr += 1;
-                if curr >= args.len() {
-                    return Err(format!("Expect log level after `--log-level`."))
-                }
-                match args[curr].as_str() {
-                    "0" | "all" => {
-                        log_level = LogLevel::All;
-                    },
-                    "1" | "warn" => {
-                        log_level = LogLevel::Warn;
-                    },
-                    "2" | "error" => {
-                        log_level = LogLevel::Error;
-                    },
-                    "3" | "never" => {
-                        log_level = LogLevel::Never;
-                    },
-                    _ => {},
-                }
+                let level = expect_value(&args, &mut curr, "log level")?;
+                log_level = Some(LogLevel::parse(level)
+                    .ok_or_else(|| format!("Unknown log level `{}`, expect `all`, `warn`, `error` or `never`.", level))?);
+            } else if args[curr].eq("--print-config") {
+                print_config = true;
+            } else if args[curr].eq("--output") {
+                let format = expect_value(&args, &mut curr, "output format")?;
+                output = OutputFormat::parse(format)
+                    .ok_or_else(|| format!("Unknown output format `{}`, expect `terminal`, `html`, `svg`, `latex` or `rtf`.", format))?;
+            } else if args[curr].eq("--emit") {
+                let what = expect_value(&args, &mut curr, "`tokens-json` or `tokens-jsonl`")?;
+                emit = Some(Emit::parse(what)
+                    .ok_or_else(|| format!("Unknown emit `{}`, expect `tokens-json` or `tokens-jsonl`.", what))?);
+            } else if args[curr].eq("--lines") {
+                line_ranges.parse(expect_value(&args, &mut curr, "line range")?)?;
+            } else if args[curr].eq("--highlight-line") {
+                highlights.parse(expect_value(&args, &mut curr, "line range")?)?;
+            } else if args[curr].eq("--grep") {
+                let pattern = expect_value(&args, &mut curr, "regex")?;
+                grep = Some(bytes::Regex::new(pattern).map_err(|e| format!("Invalid regex `{}`: {}", pattern, e))?);
+            } else if args[curr].eq("-C") || args[curr].eq("--context") {
+                context = expect_count(&args, &mut curr)?;
+            } else if args[curr].eq("-B") || args[curr].eq("--before-context") {
+                before = Some(expect_count(&args, &mut curr)?);
+            } else if args[curr].eq("-A") || args[curr].eq("--after-context") {
+                after = Some(expect_count(&args, &mut curr)?);
+            } else if args[curr].eq("--wrap") {
+                wrap = Some(true);
+            } else if args[curr].eq("--no-wrap") {
+                wrap = Some(false);
+            } else if args[curr].eq("--style") {
+                decorations = Decorations::parse(expect_value(&args, &mut curr, "style")?)?;
+            } else if args[curr].eq("--tab-width") {
+                let width = expect_value(&args, &mut curr, "tab width")?;
+                tab_width = width.parse().ok().filter(|width| *width > 0)
+                    .ok_or_else(|| format!("Invalid tab width `{}`, expect a number above 0.", width))?;
+            } else if args[curr].eq("--show-whitespace") {
+                show_whitespace = true;
+            } else if args[curr].eq("--encoding") {
+                let name = expect_value(&args, &mut curr, "encoding")?;
+                encoding = Encoding::parse(name)
+                    .ok_or_else(|| format!("Unknown encoding `{}`, expect `utf-8`, `windows-1252` or `latin-1`.", name))?;
+            } else if args[curr].eq("--binary") {
+                let mode = expect_value(&args, &mut curr, "`hex`, `skip` or `force-text`")?;
+                binary = BinaryMode::parse(mode)
+                    .ok_or_else(|| format!("Unknown binary mode `{}`, expect `hex`, `skip` or `force-text`.", mode))?;
+            } else if args[curr].eq("--html-inline") {
+                output_options.html.inline = true;
+            } else if args[curr].eq("--html-fragment") {
+                output_options.html.fragment = true;
+            } else if args[curr].eq("--svg-frame") {
+                output_options.svg.frame = true;
+            } else if args[curr].eq("--lang") {
+                lang = Some(expect_value(&args, &mut curr, "language name")?.clone());
+            } else if args[curr].eq("--theme") {
+                theme = Some(expect_value(&args, &mut curr, "theme name")?.clone());
+            } else if args[curr].eq("--color") {
+                let mode = expect_value(&args, &mut curr, "`auto`, `always` or `never`")?;
+                color_mode = Some(ColorMode::parse(mode)
+                    .ok_or_else(|| format!("Unknown color mode `{}`, expect `auto`, `always` or `never`.", mode))?);
             } else {
                 file_paths.push(args[curr].clone());
             }
             curr += 1;
         }
+        // `--wrap` or `--no-wrap`, by default only a terminal gets wrapped lines
+        output_options.ansi.wrap = wrap.unwrap_or_else(|| io::stdout().is_terminal());
+        output_options.ansi.decorations = decorations;
+        if output_options.ansi.wrap || decorations.grid || decorations.footer || !highlights.is_empty() {
+            output_options.ansi.width = terminal::width();
+        }
+        if file_paths.is_empty() {
+            // no path at all, read the source from stdin
+            file_paths.push(STDIN_PATH.to_string());
+        }
+        if emit == Some(Emit::TokensJson) && file_paths.len() > 1 {
+            // one pretty document per file wouldn't be one JSON value
+            return Err(String::from("`--emit tokens-json` takes one file, use `--emit tokens-jsonl` for several."))
+        }
+        if emit.is_none() && output.is_document(&output_options) && file_paths.len() > 1 {
+            return Err(String::from("`--output html`, `svg` and `rtf` print a whole document and take one file, `--html-fragment` takes several."))
+        }
         Ok(Arguments {
             file_paths,
             log_level,
+            lang,
+            theme,
+            color_mode,
+            print_config,
+            output,
+            output_options,
+            emit,
+            line_ranges,
+            highlights,
+            grep,
+            // `-A` and `-B` win over `-C`
+            context: (before.unwrap_or(context), after.unwrap_or(context)),
+            tab_width,
+            show_whitespace,
+            encoding,
+            binary,
         })
     }
 }
 
-pub struct Config {
-    color_map: HashMap<&'static str, (u8, u8, u8)>,
-}
-
-impl Config {
-    pub fn from_file(path: String) -> Config {
-        let color_map = color_map! {
-            title           : 255 107 107,
-            file_path       : 107 107 255,
-            keyword         : 255 107 107,
-            literal_string  : 107 255 107,
-            literal_number  : 255 255 107,
-            literal_boolean : 107 255 255,
-            type            : 255 107 255,
-            nextline        : 155 155 155,
-            note            : 155 155 155,
-            /* info      : 255 107 107,
-            note      : 107 107 255,
-            warn      : 255 107 107,
-            error     : 255 107 107, */ 
-        }; // default config
-        Config {
-            color_map, 
-        }
+/// `--flag=value` is the same as `--flag value`, and `-C3` the same as `-C 3`.
+fn split_args(args: impl Iterator<Item = String>) -> Vec<String> {
+    args.flat_map(|arg| match arg.split_once('=') {
+        Some((flag, value)) if flag.starts_with("--") => vec![ flag.to_string(), value.to_string() ],
+        // `get` rather than indexing, a path such as `中文.json` doesn't split at byte 2
+        _ if arg.len() > 2 && arg.get(..2).is_some_and(|flag| [ "-A", "-B", "-C" ].contains(&flag)) && arg[2..].bytes().all(|byte| byte.is_ascii_digit()) => {
+            vec![ arg[..2].to_string(), arg[2..].to_string() ]
+        },
+        _ => vec![ arg ],
+    })
+    .collect()
+}
+
+/// The result of writing to stdout, a reader that went away (e.g. `| head`) isn't an error.
+fn stdout_result(res: io::Result<()>) -> Result<(), String> {
+    match res {
+        // there is nobody left to tell
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        res => res.map_err(|e| format!("Stdout IO Error: {}", e)),
     }
 }
 
+/// Moves `curr` to the value of the flag at `curr`.
+fn expect_value<'a>(args: &'a [String], curr: &mut usize, what: &str) -> Result<&'a String, String> {
+    *curr += 1;
+    args.get(*curr).ok_or_else(|| format!("Expect {} after `{}`.", what, args[*curr - 1]))
+}
+
+/// The value of a context flag, such as `-C 3`.
+fn expect_count(args: &[String], curr: &mut usize) -> Result<usize, String> {
+    let count = expect_value(args, curr, "line count")?;
+    count.parse().map_err(|_| format!("Invalid line count `{}`, expect a number.", count))
+}
+
 #[derive(Clone, Debug)]
 pub struct Token {
     token: String,
     range: (usize, usize),
+    scope: Rc<String>,
     color: Rc<String>,
 }
 
 impl Token {
     pub fn new(token: String, range: (usize, usize)) -> Token {
-        Token { token, range, color: Rc::new(String::from("unknown")) }
+        let unknown = Rc::new(String::from("unknown"));
+        Token { token, range, scope: unknown.clone(), color: unknown }
     }
     pub fn colored(&self) -> bool {
         self.color.as_str() != "unknown"
     }
-    pub fn color(&mut self, color: Rc<String>) {
+    /// `scope` is the pattern name from the grammar, `color` the name it maps to in `Config::color_map`.
+    pub fn color(&mut self, scope: Rc<String>, color: Rc<String>) {
+        self.scope = scope;
         self.color = color;
     }
     pub fn as_str(&self) -> &str {
         &self.token
     }
+    /// The first and the last byte of the token, both inclusive.
+    pub fn range(&self) -> (usize, usize) {
+        self.range
+    }
+    pub fn scope(&self) -> &Rc<String> {
+        &self.scope
+    }
+    pub fn color_name(&self) -> &str {
+        &self.color
+    }
 }
 
 impl Display for Token {
@@ -150,7 +312,6 @@ impl Display for Token {
 
 lazy_static! {
     static ref RE_FILEEXT: Regex = Regex::new(".([a-zA-Z0-9]+)$").unwrap();
-    static ref STR_DEFAULT: String = String::from("default");
 }
 
 #[derive(Deserialize)]
@@ -160,26 +321,20 @@ struct LanguageMap {
 }
 
 impl LanguageMap {
-    fn from_file_path(&self, root: &String, path: &String) -> String {
-        let mut highlighting_filename;
-        match RE_FILEEXT.captures(path) {
-            Some(extname) => {
-                let default_extname = &extname[1].to_string();
-                highlighting_filename = self.highlighter_map.get(&extname[1]).unwrap_or(default_extname);
-                match std::fs::read_to_string(format!("{}/highlighting/{}.json", root, highlighting_filename)) {
-                    Ok(res) => {
-                        return res
-                    },
-                    Err(_) => {
-                        highlighting_filename = &STR_DEFAULT;
-                    }
-                }
-            },
-            None => {
-                highlighting_filename = &STR_DEFAULT;
+    /// Returns the language name and its highlighting rule.
+    fn rule_by_lang(&self, root: &String, lang: &String) -> Result<(String, String), String> {
+        std::fs::read_to_string(format!("{}/highlighting/{}.json", root, lang))
+            .map(|rule| (lang.clone(), rule))
+            .map_err(|_| format!("Unknown language `{}`: there is no `highlighting/{}.json`.", lang, lang))
+    }
+    fn rule_by_path(&self, root: &String, path: &str) -> Result<(String, String), String> {
+        if let Some(extname) = RE_FILEEXT.captures(path) {
+            let lang = self.highlighter_map.get(&extname[1]).cloned().unwrap_or(extname[1].to_string());
+            if let Ok(rule) = self.rule_by_lang(root, &lang) {
+                return Ok(rule)
             }
         }
-        std::fs::read_to_string(format!("{}/highlighting/{}.json", root, highlighting_filename)).unwrap()
+        self.rule_by_lang(root, &self.default)
     }
 }
 
@@ -188,57 +343,166 @@ pub struct App {
     root: String,
     language_map: LanguageMap,
     args: Arguments,
-    config: Config,
 }
 
 impl App {
-    pub fn new(version: &'static str, root: String, args: Arguments, config: Config) -> App {
+    pub fn new(version: &'static str, root: String, args: Arguments) -> App {
         let language_map = serde_json::from_str(std::fs::read_to_string(root.clone() + "/highlighting/language_map.json").unwrap().as_str()).unwrap();
-        App { version, root, language_map, args, config }
-    }
-    pub fn run(&self, nth: usize) -> Result<Logger, String> {
-        if let Some(file_path) = self.args.file_paths.get(nth) {
-            println!("{}", format!("{}{} - {}", colorize!(self, "Prettier@", "title"), colorize!(self, self.version, "title"), colorize!(self, file_path, "file_path")).bold());
-            match load_file(file_path) {
-                Ok(bytes) => {
-                    let mut logger = Logger::new(self.args.log_level);
-                    let mut tokenizer = Tokenizer::new(&mut logger, bytes);
-                    let (tokens, lines) = tokenizer.tokenize();
-                    let max_line_len = format!("{}", lines).len();
-                    // println!("{:#?}", tokens);
-                    let mut highlighter = Highlighter::new(&mut logger, tokens, LangHighlighter::try_parse(&self.language_map.from_file_path(&self.root, file_path)).unwrap());
-                    let tokens = highlighter.color();
-                    // println!("{:#?}", tokens);
-                    let mut line: usize = 1;
-                    print!("{}{}  ", colorize!(self, format!("{}", line), "nextline").bold(), SPACE_CHAR.repeat(max_line_len - 1));
-                    for token in tokens {
-                        match token.color.as_str() {
-                            "default" | "unknown" => {
-                                print!("{}", token);
-                            },
-                            "bold" => {
-                                print!("{}", token.token.bold().bright_black());
-                            },
-                            "symbol" => {
-                                print!("{}", token.token.italic().bright_black());
-                            },
-                            "nextline" => {
-                                line += 1;
-                                let line_len = format!("{}", line).len();
-                                print!("\n{}{}  ", colorize!(self, format!("{}", line), "nextline").bold(), SPACE_CHAR.repeat(max_line_len - line_len));
-                            },
-                            _ => {
-                                print!("{}", colorize!(self, token.token, token.color.as_str()));
-                            }
-                        }
-                    }
-                    println!();
-                    Ok(logger)
-                },
-                Err(e) => Err(e),
+        App { version, root, language_map, args }
+    }
+    /// Highlights every file in `Arguments::file_paths` in order.
+    /// Returns `false` if any of them failed.
+    pub fn run(&self) -> bool {
+        let mut success = true;
+        for file_path in &self.args.file_paths {
+            let mut logger = Logger::new(self.args.log_level.unwrap_or(LogLevel::Error));
+            let res = if self.args.print_config {
+                self.print_config(file_path, &mut logger)
+            } else {
+                self.run_file(file_path, &mut logger)
+            };
+            if logger.len() > 0 {
+                // the logs go to stderr, stdout may be read by other tools
+                eprint!("{}", logger);
+            }
+            if let Err(e) = res {
+                // there is something wrong, but the other files still get printed
+                eprintln!("{}", e.red());
+                success = false;
+            }
+            if self.args.print_config {
+                // the config of the first file is enough
+                break
+            }
+        }
+        success
+    }
+    /// Resolves the config for `file_path` and lets it decide the log level and colors from now on.
+    fn config(&self, file_path: &str, logger: &mut Logger) -> Result<Config, String> {
+        let config = Config::resolve(&self.root, file_path, self.args.config_layer(), logger)?;
+        logger.set_log_level(config.log_level);
+        // the logs and errors go through `colored`, keep them in step with the highlighting
+        colored::control::set_override(config.color_support != ColorSupport::None);
+        Ok(config)
+    }
+    fn print_config(&self, file_path: &str, logger: &mut Logger) -> Result<(), String> {
+        let config = self.config(file_path, logger)?;
+        let mut out = BufWriter::new(io::stdout().lock());
+        stdout_result(writeln!(out, "{}", config).and_then(|_| out.flush()))
+    }
+    /// Lists every color the grammar can produce that the theme doesn't define.
+    fn check_colors(&self, config: &Config, lang_highlighter: &LangHighlighter, logger: &mut Logger) {
+        let theme = config.theme.as_deref().unwrap_or("default");
+        for (scope, color) in lang_highlighter.scopes() {
+            if !config.color_map.contains_key(&color) {
+                logger.warn(format!("Grammar `{}`: scope `{}` maps to `{}`, which theme `{}` doesn't define.", lang_highlighter.name(), scope, color, theme));
             }
-        } else {
-            Err(format!(""))
         }
     }
-}
\ No newline at end of file
+    /// The lines around the matches of `--grep` within `--lines`, and the byte ranges of the matches.
+    /// `None` when nothing matches.
+    fn grep(&self, regex: &bytes::Regex, bytes: &[u8]) -> Option<(LineRanges, Vec<(usize, usize)>)> {
+        let (before, after) = self.args.context;
+        let mut ranges = LineRanges::default();
+        let mut matches = Vec::new();
+        let mut begin = 0;
+        for (i, line) in bytes.split(|byte| *byte == b'\n').enumerate() {
+            let number = i + 1;
+            if self.args.line_ranges.contains(number) {
+                let found: Vec<(usize, usize)> = regex.find_iter(line).map(|m| (begin + m.start(), begin + m.end())).collect();
+                if !found.is_empty() {
+                    ranges.push(number.saturating_sub(before).max(1), number.saturating_add(after));
+                    matches.extend(found);
+                }
+            }
+            begin += line.len() + 1;
+        }
+        if ranges.is_empty() {
+            return None
+        }
+        Some((ranges, matches))
+    }
+    fn run_file(&self, file_path: &String, logger: &mut Logger) -> Result<(), String> {
+        let config = self.config(file_path, logger)?;
+        let display_path = if file_path == STDIN_PATH { "<stdin>" } else { file_path.as_str() };
+        let bytes = load_file(file_path)?;
+        let binary = self.args.binary != BinaryMode::ForceText && hexdump::is_binary(&bytes);
+        if binary && self.args.binary == BinaryMode::Skip {
+            logger.warn(format!("`{}` looks binary, it is skipped.", display_path));
+            return Ok(())
+        }
+        // the size on disk, the rest works on the UTF-8 text or the dump
+        let size = bytes.len();
+        let (bytes, encoding, line_endings, dumped) = if binary {
+            // the rows of the dump are its lines, so `--lines` and `--grep` work on them too
+            let (text, tokens, lines) = hexdump::dump(&bytes);
+            (text, String::from("binary"), line_endings(&bytes), Some((tokens, lines)))
+        } else {
+            let (bytes, encoding) = Encoding::decode(bytes, self.args.encoding, logger);
+            let line_endings = line_endings(&bytes);
+            (bytes, encoding.to_string(), line_endings, None)
+        };
+        let (ranges, matches) = match &self.args.grep {
+            Some(regex) => match self.grep(regex, &bytes) {
+                Some(grep) => grep,
+                // nothing to show, like `grep` the file is left out
+                None => return Ok(()),
+            },
+            None => (self.args.line_ranges.clone(), Vec::new()),
+        };
+        let (lang, tokens, lines) = match dumped {
+            // the dump is colored already
+            Some((tokens, lines)) => (String::from("hex"), tokens, lines),
+            None => {
+                let (lang, rule) = match &self.args.lang {
+                    // `--lang` always wins over the extension detection
+                    Some(lang) => self.language_map.rule_by_lang(&self.root, lang)?,
+                    None => self.language_map.rule_by_path(&self.root, file_path)?,
+                };
+                let lang_highlighter = LangHighlighter::try_parse(&lang, &rule)
+                    .map_err(|e| format!("Highlighting Rule Error: {}", e))?;
+                self.check_colors(&config, &lang_highlighter, logger);
+                let mut tokenizer = Tokenizer::new(logger, bytes, lang_highlighter.char_classes());
+                let (tokens, lines) = tokenizer.tokenize();
+                let mut highlighter = Highlighter::new(logger, tokens, lang_highlighter);
+                (lang, highlighter.color(), lines)
+            },
+        };
+        let doc = Document {
+            title: display_path,
+            lang: &lang,
+            version: self.version,
+            lines: ranges.last(lines),
+            ranges: &ranges,
+            highlights: &self.args.highlights,
+            matches: &matches,
+            tab_width: self.args.tab_width,
+            show_whitespace: self.args.show_whitespace,
+            size,
+            encoding: &encoding,
+            line_endings,
+            config: &config,
+        };
+        let mut out = BufWriter::new(io::stdout().lock());
+        let res = match self.args.emit {
+            // the tokens themselves instead of the highlighted source
+            Some(emit) => tokens::emit(emit, &mut out, &doc, &tokens),
+            None => {
+                let mut renderer = self.args.output.renderer(&self.args.output_options);
+                output::render(renderer.as_mut(), &mut out, &doc, &tokens, logger)
+            },
+        }.and_then(|_| out.flush());
+        stdout_result(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_args;
+
+    #[test]
+    fn it_should_split_flags_and_keep_other_arguments() {
+        let args = [ "prettier", "中文.json", "--grep=中文", "-C3", "-A", "-Bx", "-中" ].iter().map(|arg| arg.to_string());
+        assert_eq!(split_args(args), vec![ "prettier", "中文.json", "--grep", "中文", "-C", "3", "-A", "-Bx", "-中" ]);
+    }
+}
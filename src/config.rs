@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+use serde_json::Value;
+use colored::Color;
+use crate::app::LogLevel;
+use crate::logger::{Logger, NoteFor};
+use crate::terminal::{ColorMode, ColorSupport};
+use crate::style::Style;
+use crate::theme::Theme;
+
+macro_rules! color_map {
+    ( $( $k: ident : $r: literal $g: literal $b: literal ) , * , ) => {
+        HashMap::from([
+            $ ( 
+                (stringify!($k).to_string(), Style::foreground(($r as u8, $g as u8, $b as u8))),
+            ) *
+        ])
+    };
+}
+
+pub type Rgb = (u8, u8, u8);
+
+/// Parses a color written as `"#rrggbb"` or `[r, g, b]`.
+/// `"#rgb"` and `"#rrggbbaa"` (alpha is ignored) are accepted too, imported themes use them.
+pub fn parse_rgb(value: &Value) -> Option<Rgb> {
+    match value {
+        Value::String(hex) => {
+            let hex = hex.strip_prefix('#')?;
+            match hex.len() {
+                3 => {
+                    let channel = |i: usize| u8::from_str_radix(hex.get(i..=i)?, 16).ok().map(|c| c * 17);
+                    Some((channel(0)?, channel(1)?, channel(2)?))
+                },
+                6 | 8 => {
+                    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+                    Some((channel(0)?, channel(2)?, channel(4)?))
+                },
+                _ => None,
+            }
+        },
+        Value::Array(rgb) if rgb.len() == 3 => {
+            let channel = |i: usize| rgb[i].as_u64().filter(|c| *c <= 255).map(|c| c as u8);
+            Some((channel(0)?, channel(1)?, channel(2)?))
+        },
+        _ => None,
+    }
+}
+
+/// Where a config value comes from, from the weakest to the strongest.
+#[derive(Clone, Debug)]
+pub enum Layer {
+    Default,
+    Theme(String),
+    User(String),
+    Project(String),
+    Env,
+    Cli,
+}
+
+impl Display for Layer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Layer::Default => write!(f, "default"),
+            Layer::Theme(name) => write!(f, "theme `{}`", name),
+            Layer::User(path) => write!(f, "user config `{}`", path),
+            Layer::Project(path) => write!(f, "project config `{}`", path),
+            Layer::Env => write!(f, "environment"),
+            Layer::Cli => write!(f, "command line"),
+        }
+    }
+}
+
+/// The values a single layer sets, anything left as `None` falls through to the layers below.
+pub struct ConfigLayer {
+    pub layer: Layer,
+    pub theme: Option<String>,
+    pub color_mode: Option<ColorMode>,
+    pub log_level: Option<LogLevel>,
+    pub color_map: Vec<(String, Style)>,
+}
+
+impl ConfigLayer {
+    pub fn new(layer: Layer) -> ConfigLayer {
+        ConfigLayer { layer, theme: None, color_mode: None, log_level: None, color_map: Vec::new() }
+    }
+    /// Reads a `prettier.config.json` like file, a missing file is `None`.
+    fn from_file(layer: Layer, path: &Path, logger: &mut Logger) -> Option<ConfigLayer> {
+        let json = fs::read_to_string(path).ok()?;
+        let path_str = path.to_string_lossy().to_string();
+        let mut config_layer = ConfigLayer::new(layer);
+        let object = match serde_json::from_str::<Value>(&json) {
+            Ok(Value::Object(object)) => object,
+            Ok(_) => {
+                logger.error(format!("Config Error: `{}` should be a JSON object.", path_str));
+                logger.note(String::from("The file is ignored."), NoteFor::Error);
+                return Some(config_layer)
+            },
+            Err(e) => {
+                logger.error(format!("Config Error: `{}`: {}", path_str, e));
+                logger.note(String::from("The file is ignored."), NoteFor::Error);
+                return Some(config_layer)
+            },
+        };
+        for (key, value) in &object {
+            match (key.as_str(), value) {
+                ("theme", Value::String(theme)) => {
+                    // a theme file is relative to the config that names it
+                    let theme_path = path.parent().map(|dir| dir.join(theme)).filter(|_| Theme::is_file(theme) && Path::new(theme).is_relative());
+                    config_layer.theme = Some(theme_path.map(|theme_path| theme_path.to_string_lossy().to_string()).unwrap_or(theme.clone()));
+                },
+                ("color", Value::String(mode)) if ColorMode::parse(mode).is_some() => {
+                    config_layer.color_mode = ColorMode::parse(mode);
+                },
+                ("log_level", Value::String(level)) if LogLevel::parse(level).is_some() => {
+                    config_layer.log_level = LogLevel::parse(level);
+                },
+                ("color_map", _) => merge_color_map(&mut config_layer.color_map, &path_str, value, logger),
+                ("theme", _) | ("color", _) | ("log_level", _) => {
+                    logger.warn(format!("Invalid `{}` in `{}`: `{}`, ignored.", key, path_str, value));
+                },
+                _ => {
+                    logger.warn(format!("Unknown key `{}` in `{}`.", key, path_str));
+                },
+            }
+        }
+        Some(config_layer)
+    }
+    /// `PRETTIER_THEME`, `PRETTIER_COLOR`, `PRETTIER_LOG_LEVEL` and `PRETTIER_COLOR_MAP_<NAME>`.
+    fn from_env(logger: &mut Logger) -> ConfigLayer {
+        ConfigLayer::from_vars(env::vars(), logger)
+    }
+    fn from_vars(vars: impl Iterator<Item = (String, String)>, logger: &mut Logger) -> ConfigLayer {
+        let mut config_layer = ConfigLayer::new(Layer::Env);
+        let mut vars: Vec<(String, String)> = vars.filter(|(key, _)| key.starts_with("PRETTIER_")).collect();
+        vars.sort();
+        for (key, value) in vars {
+            match key.as_str() {
+                "PRETTIER_THEME" => config_layer.theme = Some(value),
+                "PRETTIER_COLOR" => match ColorMode::parse(&value) {
+                    Some(mode) => config_layer.color_mode = Some(mode),
+                    None => logger.warn(format!("Invalid `PRETTIER_COLOR`: `{}`, ignored.", value)),
+                },
+                "PRETTIER_LOG_LEVEL" => match LogLevel::parse(&value) {
+                    Some(level) => config_layer.log_level = Some(level),
+                    None => logger.warn(format!("Invalid `PRETTIER_LOG_LEVEL`: `{}`, ignored.", value)),
+                },
+                _ => match key.strip_prefix("PRETTIER_COLOR_MAP_") {
+                    Some(name) => match Style::parse(&Value::String(value.clone())) {
+                        Ok(style) => config_layer.color_map.push((name.to_lowercase(), style)),
+                        Err(e) => logger.warn(format!("{} in `{}`, ignored.", e, key)),
+                    },
+                    None => logger.warn(format!("Unknown environment variable `{}`.", key)),
+                },
+            }
+        }
+        config_layer
+    }
+}
+
+/// `$XDG_CONFIG_HOME/prettier/config.json`, `~/.config` when it isn't set, `%APPDATA%` on Windows.
+fn user_config_path() -> Option<PathBuf> {
+    let dir = env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()).map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(dir.join("prettier").join("config.json"))
+}
+
+/// The nearest `prettier.config.json` or `.prettierrc.json` walking up from `file_path`.
+fn project_config_path(file_path: &str) -> Option<PathBuf> {
+    let cwd = env::current_dir().ok()?;
+    let dir = Path::new(file_path).parent()
+        .and_then(|dir| dir.canonicalize().ok())
+        .unwrap_or(cwd);
+    dir.ancestors()
+        .flat_map(|dir| PROJECT_CONFIG_NAMES.iter().map(move |name| dir.join(name)))
+        .find(|path| path.is_file())
+}
+
+static PROJECT_CONFIG_NAMES: [ &str; 2 ] = [ "prettier.config.json", ".prettierrc.json" ];
+
+pub struct Config {
+    pub color_map: HashMap<String, Style>,
+    /// The theme `color_map` was loaded from, `None` for the defaults.
+    pub theme: Option<String>,
+    pub color_mode: ColorMode,
+    pub log_level: LogLevel,
+    /// Resolved from `color_mode` and the terminal.
+    pub color_support: ColorSupport,
+    /// The layer each value was taken from, colors are keyed as `color_map.<name>`.
+    sources: HashMap<String, Layer>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        let mut color_map = color_map! {
+            title           : 255 107 107,
+            file_path       : 107 107 255,
+            keyword         : 255 107 107,
+            literal_string  : 107 255 107,
+            literal_number  : 255 255 107,
+            literal_boolean : 107 255 255,
+            type            : 255 107 255,
+            nextline        : 155 155 155,
+            note            : 155 155 155,
+            whitespace      : 90 90 90,
+            hex_null        : 90 90 90,
+            hex_printable   : 107 255 255,
+            hex_whitespace  : 107 255 107,
+            hex_control     : 255 255 107,
+            /* info      : 255 107 107,
+            note      : 107 107 255,
+            warn      : 255 107 107,
+            error     : 255 107 107, */ 
+        }; // default config, the same as `themes/dark.json`
+        color_map.insert(String::from("bold"), Style { foreground: Some(Color::BrightBlack), bold: true, ..Style::default() });
+        color_map.insert(String::from("symbol"), Style { foreground: Some(Color::BrightBlack), italic: true, ..Style::default() });
+        // `--show-whitespace` marks the blanks at the end of a line
+        color_map.insert(String::from("trailing_whitespace"), Style {
+            foreground: Some(Color::TrueColor { r: 255, g: 107, b: 107 }),
+            background: Some(Color::TrueColor { r: 90, g: 30, b: 30 }),
+            ..Style::default()
+        });
+        // the bytes that aren't UTF-8, printed as `\xNN`
+        color_map.insert(String::from("invalid"), Style {
+            foreground: Some(Color::TrueColor { r: 30, g: 30, b: 30 }),
+            background: Some(Color::TrueColor { r: 255, g: 107, b: 107 }),
+            ..Style::default()
+        });
+        // `--highlight-line`: the background spans the whole row, the foreground is for the line number
+        color_map.insert(String::from("highlight_line"), Style {
+            foreground: Some(Color::TrueColor { r: 255, g: 255, b: 107 }),
+            background: Some(Color::TrueColor { r: 60, g: 60, b: 30 }),
+            bold: true,
+            ..Style::default()
+        });
+        // the page of the html output, the terminal keeps its own colors
+        color_map.insert(String::from("background"), Style {
+            foreground: Some(Color::TrueColor { r: 220, g: 220, b: 220 }),
+            background: Some(Color::TrueColor { r: 30, g: 30, b: 30 }),
+            ..Style::default()
+        });
+        let sources = color_map.keys().map(|name| (format!("color_map.{}", name), Layer::Default)).collect();
+        Config {
+            color_map, 
+            theme: None,
+            color_mode: ColorMode::Auto,
+            log_level: LogLevel::Error,
+            color_support: ColorSupport::None,
+            sources,
+        }
+    }
+}
+
+impl Config {
+    /// Resolves the config for `file_path` from every layer: the defaults, the user config,
+    /// the project config, the environment and at last `cli`.
+    pub fn resolve(root: &String, file_path: &str, cli: ConfigLayer, logger: &mut Logger) -> Result<Config, String> {
+        let mut layers = Vec::new();
+        if let Some(path) = user_config_path() {
+            layers.extend(ConfigLayer::from_file(Layer::User(path.to_string_lossy().to_string()), &path, logger));
+        }
+        if let Some(path) = project_config_path(file_path) {
+            layers.extend(ConfigLayer::from_file(Layer::Project(path.to_string_lossy().to_string()), &path, logger));
+        }
+        layers.push(ConfigLayer::from_env(logger));
+        layers.push(cli);
+        Config::merge(root, layers, logger)
+    }
+    /// Stacks `layers`, from the weakest to the strongest, on top of the defaults.
+    fn merge(root: &String, layers: Vec<ConfigLayer>, logger: &mut Logger) -> Result<Config, String> {
+        let mut config = Config::default();
+        for layer in &layers {
+            if let Some(theme) = &layer.theme {
+                config.theme = Some(theme.clone());
+                config.sources.insert(String::from("theme"), layer.layer.clone());
+            }
+            if let Some(color_mode) = layer.color_mode {
+                config.color_mode = color_mode;
+                config.sources.insert(String::from("color"), layer.layer.clone());
+            }
+            if let Some(log_level) = layer.log_level {
+                config.log_level = log_level;
+                config.sources.insert(String::from("log_level"), layer.layer.clone());
+            }
+        }
+        if let Some(theme) = &config.theme {
+            let theme_layer = Layer::Theme(theme.clone());
+            for (name, style) in Theme::load(root, theme, logger)?.color_map {
+                config.sources.insert(format!("color_map.{}", name), theme_layer.clone());
+                config.color_map.insert(name, style);
+            }
+        }
+        for layer in layers {
+            for (name, style) in layer.color_map {
+                config.sources.insert(format!("color_map.{}", name), layer.layer.clone());
+                config.color_map.insert(name, style);
+            }
+        }
+        config.color_support = ColorSupport::detect(config.color_mode);
+        Ok(config)
+    }
+    fn source(&self, key: &str) -> String {
+        self.sources.get(key).unwrap_or(&Layer::Default).to_string()
+    }
+}
+
+impl Display for Config {
+    /// The merged config as JSON, with the layer of every value as a comment.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let theme = self.theme.as_ref().map_or(Value::Null, |theme| Value::String(theme.clone()));
+        writeln!(f, "{{")?;
+        writeln!(f, "    \"theme\": {}, // {}", theme, self.source("theme"))?;
+        writeln!(f, "    \"color\": \"{}\", // {}", self.color_mode, self.source("color"))?;
+        writeln!(f, "    \"log_level\": \"{}\", // {}", self.log_level, self.source("log_level"))?;
+        writeln!(f, "    \"color_map\": {{")?;
+        let mut names: Vec<&String> = self.color_map.keys().collect();
+        names.sort();
+        for name in names {
+            let key = format!("color_map.{}", name);
+            writeln!(f, "        \"{}\": {}, // {}", name, self.color_map[name].to_json(), self.source(&key))?;
+        }
+        writeln!(f, "    }}")?;
+        write!(f, "}}")
+    }
+}
+
+/// Merges a `color_map` object of a config or theme file into `color_map`.
+pub fn merge_color_map(color_map: &mut Vec<(String, Style)>, path: &str, value: &Value, logger: &mut Logger) {
+    let colors = match value.as_object() {
+        Some(colors) => colors,
+        None => {
+            logger.warn(format!("`color_map` in `{}` should be an object, ignored.", path));
+            return
+        },
+    };
+    for (name, color) in colors {
+        match Style::parse(color) {
+            Ok(style) => {
+                color_map.push((name.clone(), style));
+            },
+            Err(e) => {
+                logger.warn(format!("{} for `{}` in `{}`, ignored.", e, name, path));
+                logger.note(String::from("Colors are written as \"#rrggbb\", [r, g, b], an ANSI color name or a style object."), NoteFor::Warn);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+    use crate::app::LogLevel;
+    use crate::logger::Logger;
+    use crate::style::Style;
+    use crate::terminal::ColorMode;
+    use super::{Config, ConfigLayer, Layer};
+
+    #[test]
+    fn it_should_stack_the_layers_and_report_their_sources() {
+        let mut logger = Logger::new(LogLevel::Never);
+        let dir = env::temp_dir().join(format!("prettier-config-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let user_path = dir.join("config.json");
+        fs::write(&user_path, r##"{ "theme": "themes/mine.json", "color": "never", "log_level": "all", "color_map": { "keyword": "#010203", "type": "#040506" } }"##).unwrap();
+        let project_path = dir.join("prettier.config.json");
+        fs::write(&project_path, r##"{ "color": "always", "color_map": { "keyword": "#070809" } }"##).unwrap();
+        let mut user = ConfigLayer::from_file(Layer::User(String::from("user")), &user_path, &mut logger).unwrap();
+        let project = ConfigLayer::from_file(Layer::Project(String::from("project")), &project_path, &mut logger).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        // a theme file is relative to the config, a theme name isn't
+        assert_eq!(user.theme, Some(dir.join("themes/mine.json").to_string_lossy().to_string()));
+        let env = ConfigLayer::from_vars([
+            (String::from("PRETTIER_COLOR_MAP_TYPE"), String::from("#0a0b0c")),
+            (String::from("PRETTIER_LOG_LEVEL"), String::from("warn")),
+            (String::from("HOME"), String::from("/home")),
+        ].into_iter(), &mut logger);
+        assert_eq!(env.color_map, vec![ (String::from("type"), Style::foreground((10, 11, 12))) ]);
+        let mut cli = ConfigLayer::new(Layer::Cli);
+        cli.log_level = Some(LogLevel::Error);
+        // there is no theme file to load
+        user.theme = None;
+        let config = Config::merge(&String::new(), vec![ user, project, env, cli ], &mut logger).unwrap();
+        assert_eq!(config.color_mode, ColorMode::Always);
+        assert!(matches!(config.log_level, LogLevel::Error));
+        assert_eq!(config.color_map["keyword"], Style::foreground((7, 8, 9)));
+        assert_eq!(config.color_map["type"], Style::foreground((10, 11, 12)));
+        assert_eq!(config.source("color"), "project config `project`");
+        assert_eq!(config.source("log_level"), "command line");
+        assert_eq!(config.source("color_map.keyword"), "project config `project`");
+        assert_eq!(config.source("color_map.type"), "environment");
+        assert_eq!(config.source("color_map.note"), "default");
+        assert_eq!(config.source("theme"), "default");
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt::Display;
+use crate::logger::Logger;
+
+/// The encodings a file is read as, everything is transcoded to UTF-8 before tokenizing.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Encoding {
+    Utf8,
+    /// UTF-8 behind a byte order mark.
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Windows1252,
+    Latin1,
+}
+
+/// What Windows-1252 puts at 0x80-0x9F, where Latin-1 has the C1 controls.
+/// The 5 holes keep their C1 control, like browsers do.
+static WINDOWS_1252: [ char; 32 ] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+    '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+impl Encoding {
+    /// `--encoding`, the legacy encoding of the files that aren't UTF-8.
+    pub fn parse(name: &str) -> Option<Encoding> {
+        match name.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(Encoding::Utf8),
+            "windows-1252" | "cp1252" => Some(Encoding::Windows1252),
+            "latin-1" | "latin1" | "iso-8859-1" => Some(Encoding::Latin1),
+            _ => None,
+        }
+    }
+    /// Reads `bytes` as UTF-8 with or without a BOM, or as UTF-16 with a BOM.
+    /// Anything else that isn't UTF-8 is read as `fallback`, `Utf8` keeps the bytes for the tokenizer to print as `\xNN`.
+    pub fn decode(bytes: Vec<u8>, fallback: Encoding, logger: &mut Logger) -> (Vec<u8>, Encoding) {
+        if let Some(rest) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+            return (rest.to_vec(), Encoding::Utf8Bom)
+        }
+        if let Some(rest) = bytes.strip_prefix(b"\xFF\xFE") {
+            return (decode_utf16(rest, u16::from_le_bytes, logger), Encoding::Utf16Le)
+        }
+        if let Some(rest) = bytes.strip_prefix(b"\xFE\xFF") {
+            return (decode_utf16(rest, u16::from_be_bytes, logger), Encoding::Utf16Be)
+        }
+        if fallback == Encoding::Utf8 || std::str::from_utf8(&bytes).is_ok() {
+            return (bytes, Encoding::Utf8)
+        }
+        let text: String = bytes.iter().map(|byte| match (fallback, byte) {
+            (Encoding::Windows1252, 0x80..=0x9F) => WINDOWS_1252[(byte - 0x80) as usize],
+            _ => *byte as char,
+        }).collect();
+        (text.into_bytes(), fallback)
+    }
+}
+
+/// Unpaired surrogates and a dangling last byte become U+FFFD.
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16, logger: &mut Logger) -> Vec<u8> {
+    let units = bytes.chunks_exact(2).map(|pair| unit([ pair[0], pair[1] ]));
+    let mut replaced = 0;
+    let mut text: String = char::decode_utf16(units)
+        .map(|ch| ch.unwrap_or_else(|_| {
+            replaced += 1;
+            char::REPLACEMENT_CHARACTER
+        }))
+        .collect();
+    if bytes.len() % 2 == 1 {
+        replaced += 1;
+        text.push(char::REPLACEMENT_CHARACTER);
+    }
+    if replaced > 0 {
+        logger.warn(format!("{} invalid UTF-16 sequence(s) are printed as `\u{FFFD}`.", replaced));
+    }
+    text.into_bytes()
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Encoding::Utf8 => write!(f, "UTF-8"),
+            Encoding::Utf8Bom => write!(f, "UTF-8 BOM"),
+            Encoding::Utf16Le => write!(f, "UTF-16LE"),
+            Encoding::Utf16Be => write!(f, "UTF-16BE"),
+            Encoding::Windows1252 => write!(f, "Windows-1252"),
+            Encoding::Latin1 => write!(f, "Latin-1"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::LogLevel;
+    use crate::logger::Logger;
+    use super::Encoding;
+
+    #[test]
+    fn it_should_detect_and_transcode() {
+        let mut logger = Logger::new(LogLevel::All);
+        let mut decode = |bytes: &[u8], fallback| {
+            let (text, encoding) = Encoding::decode(bytes.to_vec(), fallback, &mut logger);
+            (String::from_utf8(text).unwrap(), encoding)
+        };
+        assert_eq!(decode(b"\xEF\xBB\xBFfn", Encoding::Utf8), (String::from("fn"), Encoding::Utf8Bom));
+        assert_eq!(decode(b"\xFF\xFE{\x00\xE9\x00}\x00", Encoding::Utf8), (String::from("{é}"), Encoding::Utf16Le));
+        assert_eq!(decode(b"\xFE\xFF\xD8\x3D\xDE\x00", Encoding::Latin1), (String::from("😀"), Encoding::Utf16Be));
+        assert_eq!(decode(b"caf\xE9 \x80", Encoding::Windows1252), (String::from("café €"), Encoding::Windows1252));
+        assert_eq!(decode(b"caf\xE9 \x80", Encoding::Latin1), (String::from("café \u{80}"), Encoding::Latin1));
+        assert_eq!(decode("café".as_bytes(), Encoding::Latin1), (String::from("café"), Encoding::Utf8));
+        assert_eq!(decode(b"\xFF\xFEa\x00\x00\xD8b", Encoding::Utf8), (String::from("a\u{FFFD}\u{FFFD}"), Encoding::Utf16Le));
+        assert_eq!(logger.len(), 1);
+    }
+}
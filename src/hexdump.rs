@@ -0,0 +1,150 @@
+use std::rc::Rc;
+use crate::app::Token;
+
+/// `--binary=hex|skip|force-text`
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum BinaryMode {
+    /// A hexdump instead of the source.
+    Hex,
+    /// The file is left out.
+    Skip,
+    /// Tokenized like any other file.
+    ForceText,
+}
+
+impl BinaryMode {
+    pub fn parse(mode: &str) -> Option<BinaryMode> {
+        match mode {
+            "hex" => Some(BinaryMode::Hex),
+            "skip" => Some(BinaryMode::Skip),
+            "force-text" => Some(BinaryMode::ForceText),
+            _ => None,
+        }
+    }
+}
+
+/// Only the head of the file is looked at, like `grep` and `git` do.
+static SNIFF_LEN: usize = 8192;
+static BYTES_PER_ROW: usize = 16;
+
+/// The scope and the color of every part of a row.
+static OFFSET: (&str, &str) = ("hex.offset", "note");
+static SEPARATOR: (&str, &str) = ("hex.separator", "note");
+static NULL: (&str, &str) = ("hex.null", "hex_null");
+static PRINTABLE: (&str, &str) = ("hex.printable", "hex_printable");
+static WHITESPACE: (&str, &str) = ("hex.whitespace", "hex_whitespace");
+/// The other ASCII controls and everything above 0x7F.
+static CONTROL: (&str, &str) = ("hex.control", "hex_control");
+static PLAIN: (&str, &str) = ("unknown", "unknown");
+
+/// A NUL, or more than 1 in 10 control bytes other than the usual blanks and escapes.
+/// A byte order mark says the file is text, a UTF-16 one is full of NULs.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    if [ &b"\xEF\xBB\xBF"[..], b"\xFF\xFE", b"\xFE\xFF" ].iter().any(|bom| bytes.starts_with(bom)) {
+        return false
+    }
+    let head = &bytes[..bytes.len().min(SNIFF_LEN)];
+    if head.contains(&0) {
+        return true
+    }
+    let controls = head.iter()
+        .filter(|byte| (byte.is_ascii_control() && !b"\t\n\r\x0C\x1B\x08".contains(byte)) || **byte == 0x7F)
+        .count();
+    controls * 10 > head.len()
+}
+
+fn class(byte: u8) -> (&'static str, &'static str) {
+    match byte {
+        0 => NULL,
+        b' ' | b'\t' | b'\n' | b'\r' | b'\x0C' => WHITESPACE,
+        0x21..=0x7E => PRINTABLE,
+        _ => CONTROL,
+    }
+}
+
+/// Builds the dump, the neighbouring pieces of the same class become one token.
+struct Dump {
+    text: String,
+    tokens: Vec<Token>,
+    /// Where the pending token starts in `text`.
+    start: usize,
+    class: (&'static str, &'static str),
+}
+
+impl Dump {
+    fn push(&mut self, text: &str, class: (&'static str, &'static str)) {
+        if class != self.class {
+            self.flush();
+            self.class = class;
+        }
+        self.text.push_str(text);
+    }
+    fn flush(&mut self) {
+        if self.start == self.text.len() {
+            return
+        }
+        let mut token = Token::new(self.text[self.start..].to_string(), (self.start, self.text.len() - 1));
+        if self.class != PLAIN {
+            token.color(Rc::new(self.class.0.to_string()), Rc::new(self.class.1.to_string()));
+        }
+        self.tokens.push(token);
+        self.start = self.text.len();
+    }
+    fn newline(&mut self) {
+        self.flush();
+        self.push("\n", ("nextline", "nextline"));
+        self.flush();
+    }
+}
+
+/// A row of 16 bytes is `offset  hex hex ...  │ascii│`, the rows are the lines of the dump.
+/// Returns the text of the dump, its tokens and the number of rows.
+pub fn dump(bytes: &[u8]) -> (Vec<u8>, Vec<Token>, usize) {
+    let mut dump = Dump { text: String::new(), tokens: Vec::new(), start: 0, class: PLAIN };
+    let rows = bytes.len().div_ceil(BYTES_PER_ROW);
+    for (i, row) in bytes.chunks(BYTES_PER_ROW).enumerate() {
+        if i > 0 {
+            dump.newline();
+        }
+        dump.push(&format!("{:08x}", i * BYTES_PER_ROW), OFFSET);
+        dump.push("  ", PLAIN);
+        for column in 0..BYTES_PER_ROW {
+            match row.get(column) {
+                Some(byte) => dump.push(&format!("{:02x}", byte), class(*byte)),
+                None => dump.push("  ", PLAIN),
+            }
+            // an extra space between the two halves
+            dump.push(if column == BYTES_PER_ROW / 2 - 1 { "  " } else { " " }, PLAIN);
+        }
+        dump.push(" │", SEPARATOR);
+        for byte in row {
+            let ch = if class(*byte) == PRINTABLE || *byte == b' ' { *byte as char } else { '.' };
+            dump.push(ch.encode_utf8(&mut [0; 4]), class(*byte));
+        }
+        dump.push("│", SEPARATOR);
+    }
+    dump.flush();
+    (dump.text.into_bytes(), dump.tokens, rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dump, is_binary};
+
+    #[test]
+    fn it_should_dump_binary_files() {
+        assert!(is_binary(b"\x7FELF\x02\x01\x01\x00"));
+        assert!(!is_binary(b"fn main() {\n\tprintln!(\"\x1B[1m\");\n}\n"));
+        assert!(!is_binary(b"\xFF\xFEa\x00b\x00"));
+        assert!(is_binary(b"\x01\x02\x03 text"));
+        let (text, tokens, rows) = dump(b"\x7FELF\x00\x00\n abcdefghijklmnop");
+        assert_eq!(String::from_utf8(text.clone()).unwrap(), concat!(
+            "00000000  7f 45 4c 46 00 00 0a 20  61 62 63 64 65 66 67 68  │.ELF... abcdefgh│\n",
+            "00000010  69 6a 6b 6c 6d 6e 6f 70                           │ijklmnop│",
+        ));
+        assert_eq!(rows, 2);
+        assert_eq!(tokens.iter().map(|token| token.as_str()).collect::<String>().into_bytes(), text);
+        let colors: Vec<&str> = tokens.iter().take(8).map(|token| token.color_name()).collect();
+        assert_eq!(colors, vec![ "note", "unknown", "hex_control", "unknown", "hex_printable", "unknown", "hex_printable", "unknown" ]);
+    }
+}
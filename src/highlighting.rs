@@ -18,6 +18,10 @@ impl<'a> Highlighter<'a> {
         let mut begin_id = 0;
         for token in &self.tokens {
             if token.colored() {
+                // a line break keeps its color, but it still ends a pattern such as a `//` note
+                if token.color_name() == "nextline" && begin_color.is_some() && self.highlighter.end_pattern(token.as_str(), begin_id) {
+                    begin_color = None;
+                }
                 tokens.push(token.clone());
                 continue
             }
@@ -28,7 +32,7 @@ impl<'a> Highlighter<'a> {
                         if self.highlighter.include_end(begin_id) {
                             let mut colored_token = token.clone();
                             let color = self.highlighter.try_getcolor(begin_color.as_ref().unwrap().clone());
-                            colored_token.color(Rc::new(color.unwrap_or(&*begin_color.as_ref().unwrap().clone()).to_string()));
+                            colored_token.color(begin_color.as_ref().unwrap().clone(), Rc::new(color.unwrap_or(&*begin_color.as_ref().unwrap().clone()).to_string()));
                             tokens.push(colored_token);
                             begin_color = None;
                             continue
@@ -38,7 +42,7 @@ impl<'a> Highlighter<'a> {
                     } else {
                         let mut colored_token = token.clone();
                         let color = self.highlighter.try_getcolor(begin_c.clone());
-                        colored_token.color(Rc::new(color.unwrap_or(&*begin_c.clone()).to_string()));
+                        colored_token.color(begin_c.clone(), Rc::new(color.unwrap_or(&*begin_c.clone()).to_string()));
                         tokens.push(colored_token);
                         continue
                     }
@@ -56,7 +60,7 @@ impl<'a> Highlighter<'a> {
                         if self.highlighter.include_first(begin_id) {
                             let mut colored_token = token.clone();
                             let color = self.highlighter.try_getcolor(begin_color.as_ref().unwrap().clone());
-                            colored_token.color(Rc::new(color.unwrap_or(&*begin_color.as_ref().unwrap().clone()).to_string()));
+                            colored_token.color(begin_color.as_ref().unwrap().clone(), Rc::new(color.unwrap_or(&*begin_color.as_ref().unwrap().clone()).to_string()));
                             tokens.push(colored_token);
                             continue
                         }
@@ -75,10 +79,36 @@ impl<'a> Highlighter<'a> {
                 }
                 let mut colored_token = token.clone();
                 let color = self.highlighter.try_getcolor(matched[0].clone());
-                colored_token.color(Rc::new(color.unwrap_or(&*matched[0]).clone()));
+                colored_token.color(matched[0].clone(), Rc::new(color.unwrap_or(&*matched[0]).clone()));
                 tokens.push(colored_token)
             }
         }
         tokens
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::app::LogLevel;
+    use crate::language_pattern::LangHighlighter;
+    use crate::logger::Logger;
+    use crate::tokenizer::Tokenizer;
+    use super::Highlighter;
+
+    #[test]
+    fn it_should_end_a_note_at_the_line_break() {
+        let mut logger = Logger::new(LogLevel::Never);
+        let rule = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/highlighting/rust.json")).unwrap();
+        let lang_highlighter = LangHighlighter::try_parse("rust", &rule).unwrap();
+        let (tokens, _) = Tokenizer::new(&mut logger, b"{\n    // c\n    let x = 5;\n}\n".to_vec(), lang_highlighter.char_classes()).tokenize();
+        let tokens = Highlighter::new(&mut logger, tokens, lang_highlighter).color();
+        let colors: Vec<(&str, &str)> = tokens.iter()
+            .filter(|token| !token.as_str().trim().is_empty())
+            .map(|token| (token.as_str(), token.color_name()))
+            .collect();
+        assert_eq!(colors, vec![
+            ("{", "unknown"), ("//", "note"), ("c", "note"),
+            ("let", "keyword"), ("x", "symbol"), ("=", "unknown"), ("5", "literal_number"), (";", "unknown"), ("}", "unknown"),
+        ]);
+    }
+}
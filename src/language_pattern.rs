@@ -1,6 +1,6 @@
 use std::{collections::HashMap, rc::Rc};
 use regex::Regex;
-use serde::{Deserialize};
+use serde::{Deserialize, de::Error};
 use serde_json::Result;
 
 #[derive(Deserialize, Debug, Clone)]
@@ -18,31 +18,90 @@ struct PatternBeginEnd {
     include_end: bool,
 }
 
+/// The characters the tokenizer splits the source by, a grammar only declares the sets it changes.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct CharClasses {
+    /// Taken out of the other sets, such as `-` for CSS or `$` for shell.
+    pub word: String,
+    /// A run of them is split by the longest match in `operator_table`, or else into single characters.
+    pub operator: String,
+    pub blank: String,
+    /// Always a token of its own.
+    pub quote: String,
+    /// The operators longer than one character, such as `::` or `..=`.
+    /// The ones with a character outside of `operator` never match.
+    pub operator_table: Vec<String>,
+}
+
+impl Default for CharClasses {
+    fn default() -> CharClasses {
+        CharClasses {
+            word: String::new(),
+            operator: String::from("+-*/~`!@#$%^&()=[]{}|;:?.,<>\\"),
+            blank: String::from(" \t\r"),
+            quote: String::from("'\""),
+            // the C family, a grammar without a table of its own still gets its comments and arrows
+            operator_table: [
+                "//", "/*", "*/", "::", "->", "=>", "...", "..", "==", "!=", "<=", ">=", "&&", "||",
+                "++", "--", "<<", ">>", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=",
+            ].iter().map(|operator| operator.to_string()).collect(),
+        }
+    }
+}
+
 #[derive(Deserialize, Debug)]
 pub struct LangPatterns {
     color_map: HashMap<String, String>,
     pattern_regex: Vec<PatternRegex>,
     pattern_begin_end: Vec<PatternBeginEnd>,
+    #[serde(default)]
+    char_classes: CharClasses,
 }
 
 impl LangPatterns {
     pub fn try_parse(json: &str) -> Result<LangPatterns> {
-        serde_json::from_str(json)
+        let lang_patterns: LangPatterns = serde_json::from_str(json)?;
+        let classes = &lang_patterns.char_classes;
+        // the tokenizer works on bytes, a multi-byte character would be cut in pieces
+        if [ &classes.word, &classes.operator, &classes.blank, &classes.quote ].iter().any(|set| !set.is_ascii() || set.contains('\n')) {
+            return Err(serde_json::Error::custom("`char_classes` only takes ASCII characters other than `\\n`"))
+        }
+        Ok(lang_patterns)
     }
 }
 
 pub struct LangHighlighter {
+    name: String,
     regex_map: HashMap<String, Regex>,
     lang_patterns: LangPatterns,
 }
 
 impl LangHighlighter {
-    pub fn try_parse(json: &str) -> Result<LangHighlighter> {
+    pub fn try_parse(name: &str, json: &str) -> Result<LangHighlighter> {
         Ok(LangHighlighter {
+            name: name.to_string(),
             regex_map: HashMap::new(),
             lang_patterns: LangPatterns::try_parse(json)?,
         })
     }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn char_classes(&self) -> &CharClasses {
+        &self.lang_patterns.char_classes
+    }
+    /// Every scope the patterns can produce and the color it maps to, sorted and without duplicates.
+    pub fn scopes(&self) -> Vec<(String, String)> {
+        let names = self.lang_patterns.pattern_regex.iter().map(|pattern| &pattern.name)
+            .chain(self.lang_patterns.pattern_begin_end.iter().map(|pattern| &pattern.name));
+        let mut scopes: Vec<(String, String)> = names
+            .map(|name| (name.clone(), self.lang_patterns.color_map.get(name).unwrap_or(name).clone()))
+            .collect();
+        scopes.sort();
+        scopes.dedup();
+        scopes
+    }
     #[inline]
     fn regex(&mut self, regex: &String) -> &Regex {
         if !self.regex_map.contains_key(regex) {
@@ -52,6 +52,10 @@ impl Logger {
             log_level,
         }
     }
+    /// The level only filters what is printed, so it can be decided after logging.
+    pub fn set_log_level(&mut self, log_level: LogLevel) {
+        self.log_level = log_level;
+    }
     pub fn info(&mut self, msg: String) {
         self.logs.push(Log::Info(msg));
     }
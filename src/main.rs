@@ -1,14 +1,25 @@
 mod app;
+mod config;
 mod logger;
 mod tokenizer;
+mod encoding;
+mod hexdump;
 mod highlighting;
 mod language_pattern;
 mod project_root;
+mod theme;
+mod theme_import;
+mod plist;
+mod style;
+mod terminal;
+mod output;
 
 #[macro_use]
 extern crate lazy_static;
 
-use app::{ Arguments, Config, App };
+use std::process;
+
+use app::{ Arguments, App };
 use colored::Colorize;
 use project_root::get_project_root;
 
@@ -18,23 +29,15 @@ fn main() {
     let project_root = get_project_root().unwrap().to_str().unwrap().to_string();
     match Arguments::from_env() {
         Ok(args) => {
-            let config = Config::from_file(project_root.clone() + "/prettier.config.json");
-            let app = App::new(VERSION, project_root, args, config);
-            match app.run(0) {
-                Ok(logger) => {
-                    if logger.len() > 0 {
-                        // there are some logs
-                        println!("{}", logger);
-                    }
-                },
-                Err(e) => {
-                    // there is something wrong
-                    println!("{}", e.red());
-                }
-            };
+            let app = App::new(VERSION, project_root, args);
+            if !app.run() {
+                // at least one of the files failed
+                process::exit(1);
+            }
         },
         Err(e) => {
-            println!("{}", e.red());
+            eprintln!("{}", e.red());
+            process::exit(1);
         }
     }
 }
@@ -0,0 +1,242 @@
+use std::io::{self, Write};
+use colored::Colorize;
+use crate::style::Style;
+use crate::terminal::ColorSupport;
+use super::{char_width, Document, Renderer};
+
+lazy_static! {
+    static ref SPACE_CHAR: String = String::from(" ");
+}
+
+macro_rules! colorize {
+    ( $config : expr , $str : expr , $color : expr ) => {
+        match $config.color_map.get($color) {
+            Some(style) => style.paint(&$str, $config.color_support),
+            None => $str.to_string(),
+        }
+    };
+}
+
+/// `--style=numbers,grid,header` or `--style=plain`, which parts are drawn around the code.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Decorations {
+    /// The line numbers in the gutter.
+    pub numbers: bool,
+    /// The rules around the code and the line between the gutter and the code.
+    pub grid: bool,
+    /// The file path and what is known about the file.
+    pub header: bool,
+    /// The rule below the code, `grid` draws it too.
+    pub footer: bool,
+}
+
+impl Default for Decorations {
+    fn default() -> Decorations {
+        Decorations { numbers: true, grid: false, header: true, footer: false }
+    }
+}
+
+impl Decorations {
+    pub fn parse(style: &str) -> Result<Decorations, String> {
+        let mut decorations = Decorations { numbers: false, grid: false, header: false, footer: false };
+        for part in style.split(',') {
+            match part.trim() {
+                "numbers" => decorations.numbers = true,
+                "grid" => decorations.grid = true,
+                "header" => decorations.header = true,
+                "footer" => decorations.footer = true,
+                "plain" => {},
+                part => return Err(format!("Unknown style `{}`, expect `numbers`, `grid`, `header`, `footer` or `plain`.", part)),
+            }
+        }
+        Ok(decorations)
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct AnsiOptions {
+    /// The columns of the terminal, for the grid and the wrapping.
+    pub width: usize,
+    /// Breaks the lines at `width`, gutter included.
+    pub wrap: bool,
+    pub decorations: Decorations,
+}
+
+impl Default for AnsiOptions {
+    fn default() -> AnsiOptions {
+        AnsiOptions { width: 80, wrap: false, decorations: Decorations::default() }
+    }
+}
+
+/// `1.5 KiB`
+fn human_size(bytes: usize) -> String {
+    let units = [ "KiB", "MiB", "GiB" ];
+    if bytes < 1024 {
+        return format!("{} B", bytes)
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < units.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, units[unit])
+}
+
+/// The terminal output: a header, a gutter with the line numbers and the tokens painted with SGR sequences.
+pub struct AnsiRenderer {
+    options: AnsiOptions,
+    max_line_len: usize,
+    /// The columns left for the text once the gutter is drawn.
+    text_width: Option<usize>,
+    /// The display column of the next character on the current row.
+    column: usize,
+    /// The `highlight_line` style while the current line is one of `--highlight-line`.
+    highlight: Option<Style>,
+}
+
+impl AnsiRenderer {
+    pub fn new(options: AnsiOptions) -> AnsiRenderer {
+        AnsiRenderer { options, max_line_len: 1, text_width: None, column: 0, highlight: None }
+    }
+    /// On a highlighted line the text keeps its colors but takes the background of the line.
+    fn paint(&self, out: &mut dyn Write, doc: &Document, text: &str, style: Option<&Style>) -> io::Result<()> {
+        let style = match (style, self.highlight) {
+            (style, Some(highlight)) => {
+                let style = style.copied().unwrap_or_default();
+                Some(Style { background: style.background.or(highlight.background), ..style })
+            },
+            (style, None) => style.copied(),
+        };
+        match style {
+            Some(style) => write!(out, "{}", style.paint(text, doc.config.color_support)),
+            None => write!(out, "{}", text),
+        }
+    }
+    /// Paints the rest of a highlighted row up to the edge of the terminal.
+    fn fill(&self, out: &mut dyn Write, doc: &Document) -> io::Result<()> {
+        if self.highlight.is_none() {
+            return Ok(())
+        }
+        let rest = self.options.width.saturating_sub(self.gutter_width() + self.column);
+        self.paint(out, doc, &SPACE_CHAR.repeat(rest), None)
+    }
+    fn gutter_width(&self) -> usize {
+        match (self.options.decorations.numbers, self.options.decorations.grid) {
+            (true, true) => self.max_line_len + 3,
+            (true, false) => self.max_line_len + 2,
+            (false, _) => 0,
+        }
+    }
+    /// Pads `label` to the width of the line numbers and draws the separator after it.
+    fn gutter(&self, out: &mut dyn Write, doc: &Document, label: &str, bold: bool) -> io::Result<()> {
+        if !self.options.decorations.numbers {
+            return Ok(())
+        }
+        // the number of a highlighted line stands out as well
+        let color = if self.highlight.is_some() { "highlight_line" } else { "nextline" };
+        let label = format!("{}{}", label, SPACE_CHAR.repeat(self.max_line_len.saturating_sub(label.chars().count())));
+        let label = colorize!(doc.config, label, color);
+        let label = if bold { label.bold().to_string() } else { label };
+        let separator = if self.options.decorations.grid { " │ " } else { "  " };
+        if self.options.decorations.grid || self.highlight.is_some() {
+            write!(out, "{}{}", label, colorize!(doc.config, separator, color))
+        } else {
+            write!(out, "{}{}", label, separator)
+        }
+    }
+    /// A rule across the terminal, `joint` is where it meets the line between the gutter and the code.
+    fn rule(&self, out: &mut dyn Write, doc: &Document, joint: &str) -> io::Result<()> {
+        let width = self.options.width;
+        let rule = if self.options.decorations.numbers {
+            let left = self.max_line_len + 1;
+            format!("{}{}{}", "─".repeat(left), joint, "─".repeat(width.saturating_sub(left + 1)))
+        } else {
+            "─".repeat(width)
+        };
+        writeln!(out, "{}", colorize!(doc.config, rule, "nextline"))
+    }
+    /// Starts a new row with a `↪` in the gutter.
+    fn continuation(&mut self, out: &mut dyn Write, doc: &Document) -> io::Result<()> {
+        self.fill(out, doc)?;
+        self.column = 0;
+        writeln!(out)?;
+        self.gutter(out, doc, "↪", true)
+    }
+}
+
+impl Renderer for AnsiRenderer {
+    fn start(&mut self, out: &mut dyn Write, doc: &Document) -> io::Result<()> {
+        let config = doc.config;
+        let decorations = self.options.decorations;
+        self.max_line_len = format!("{}", doc.lines).len();
+        self.highlight = None;
+        if self.options.wrap {
+            self.text_width = Some(self.options.width.saturating_sub(self.gutter_width()).max(1));
+        }
+        if decorations.grid {
+            self.rule(out, doc, "┬")?;
+        }
+        if decorations.header {
+            let title = format!("{}{} - {}", colorize!(config, "Prettier@", "title"), colorize!(config, doc.version, "title"), colorize!(config, doc.title, "file_path")).bold();
+            let meta = format!("{} · {} · {} · {}", doc.lang, human_size(doc.size), doc.encoding, doc.line_endings);
+            for row in [ title.to_string(), colorize!(config, meta, "note") ] {
+                if decorations.grid {
+                    self.gutter(out, doc, "", false)?;
+                }
+                writeln!(out, "{}", row)?;
+            }
+            if decorations.grid {
+                self.rule(out, doc, "┼")?;
+            }
+        }
+        Ok(())
+    }
+    fn line_start(&mut self, out: &mut dyn Write, doc: &Document, line: usize) -> io::Result<()> {
+        self.column = 0;
+        // without colors there is no background to draw, padding the row would only leave trailing blanks
+        let shown = doc.config.color_support != ColorSupport::None && doc.highlights.contains(line);
+        self.highlight = match doc.highlights.is_empty() || !shown {
+            true => None,
+            false => doc.config.color_map.get("highlight_line").copied(),
+        };
+        self.gutter(out, doc, &format!("{}", line), true)
+    }
+    fn token(&mut self, out: &mut dyn Write, doc: &Document, text: &str, _color: &str, style: Option<&Style>) -> io::Result<()> {
+        let text_width = match self.text_width {
+            Some(text_width) => text_width,
+            None => {
+                self.column += text.chars().map(char_width).sum::<usize>();
+                return self.paint(out, doc, text, style)
+            },
+        };
+        // every row paints its own piece, so the colors don't run into the gutter
+        let mut piece = String::new();
+        for ch in text.chars() {
+            let width = char_width(ch);
+            if self.column > 0 && self.column + width > text_width {
+                self.paint(out, doc, &piece, style)?;
+                piece.clear();
+                self.continuation(out, doc)?;
+            }
+            piece.push(ch);
+            self.column += width;
+        }
+        self.paint(out, doc, &piece, style)
+    }
+    fn line_end(&mut self, out: &mut dyn Write, doc: &Document, _line: usize) -> io::Result<()> {
+        self.fill(out, doc)?;
+        writeln!(out)
+    }
+    fn skipped(&mut self, out: &mut dyn Write, doc: &Document) -> io::Result<()> {
+        writeln!(out, "{}", colorize!(doc.config, "...", "nextline"))
+    }
+    fn end(&mut self, out: &mut dyn Write, doc: &Document) -> io::Result<()> {
+        let decorations = self.options.decorations;
+        if decorations.grid || decorations.footer {
+            // without the grid there is no gutter line to join
+            self.rule(out, doc, if decorations.grid { "┴" } else { "─" })?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+use crate::config::Config;
+use crate::style::Style;
+use super::{css_class, escape, hex, inverted, Document, Renderer};
+
+#[derive(Copy, Clone, Default)]
+pub struct HtmlOptions {
+    /// `style=` attributes instead of classes, for pasting into emails and wikis.
+    pub inline: bool,
+    /// Only the `<pre>` block instead of a whole document.
+    pub fragment: bool,
+}
+
+/// The CSS declarations of a style, such as `color: #ff6b6b; font-weight: bold;`.
+pub fn css(style: &Style) -> String {
+    let mut declarations = Vec::new();
+    if let Some(color) = style.foreground {
+        declarations.push(format!("color: {};", hex(color)));
+    }
+    if let Some(color) = style.background {
+        declarations.push(format!("background-color: {};", hex(color)));
+    }
+    if style.bold {
+        declarations.push(String::from("font-weight: bold;"));
+    }
+    if style.italic {
+        declarations.push(String::from("font-style: italic;"));
+    }
+    if style.underline || style.strikethrough {
+        let lines: Vec<&str> = [ (style.underline, "underline"), (style.strikethrough, "line-through") ].iter()
+            .filter(|(flag, _)| *flag)
+            .map(|(_, line)| *line)
+            .collect();
+        declarations.push(format!("text-decoration: {};", lines.join(" ")));
+    }
+    if style.dim {
+        declarations.push(String::from("opacity: 0.6;"));
+    }
+    declarations.join(" ")
+}
+
+/// The rule of the `<pre>` block and one rule per color.
+fn stylesheet(config: &Config) -> String {
+    let mut res = String::new();
+    let pre = config.color_map.get("background").map(css).unwrap_or_default();
+    res.push_str(&format!(".prettier {{ {} padding: 1em; font-family: monospace; }}\n", pre));
+    let gutter = config.color_map.get("nextline").map(css).unwrap_or_default();
+    res.push_str(&format!(".prettier .pr-gutter {{ {} text-decoration: none; user-select: none; }}\n", gutter));
+    res.push_str(&format!(".prettier .pr-grep_match {{ {} }}\n", css(&inverted(None, config))));
+    let names: BTreeSet<&String> = config.color_map.keys().collect();
+    for name in names {
+        res.push_str(&format!(".prettier .{} {{ {} }}\n", css_class(name), css(&config.color_map[name])));
+    }
+    res
+}
+
+/// HTML with a `<pre>` block, every line has an anchor such as `#L12`.
+pub struct HtmlRenderer {
+    options: HtmlOptions,
+    max_line_len: usize,
+    /// Whether a row is printed already, the newline goes between the rows.
+    started: bool,
+}
+
+impl HtmlRenderer {
+    pub fn new(options: HtmlOptions) -> HtmlRenderer {
+        HtmlRenderer { options, max_line_len: 1, started: false }
+    }
+    /// Starts a row, one newline after the last would show as an empty line.
+    fn row(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        if self.started {
+            writeln!(out)?;
+        }
+        self.started = true;
+        Ok(())
+    }
+}
+
+impl Renderer for HtmlRenderer {
+    fn start(&mut self, out: &mut dyn Write, doc: &Document) -> io::Result<()> {
+        self.max_line_len = format!("{}", doc.lines).len();
+        if !self.options.fragment {
+            writeln!(out, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">")?;
+            writeln!(out, "<title>{}</title>", escape(doc.title))?;
+        }
+        if !self.options.inline {
+            writeln!(out, "<style>\n{}</style>", stylesheet(doc.config))?;
+        }
+        if !self.options.fragment {
+            writeln!(out, "</head>\n<body>")?;
+        }
+        if self.options.inline {
+            let pre = doc.config.color_map.get("background").map(css).unwrap_or_default();
+            write!(out, "<pre style=\"{} padding: 1em; font-family: monospace;\"><code>", pre)
+        } else {
+            write!(out, "<pre class=\"prettier\"><code>")
+        }
+    }
+    fn line_start(&mut self, out: &mut dyn Write, doc: &Document, line: usize) -> io::Result<()> {
+        self.row(out)?;
+        let number = format!("{:<width$}", line, width = self.max_line_len);
+        if self.options.inline {
+            let gutter = doc.config.color_map.get("nextline").map(css).unwrap_or_default();
+            write!(out, "<span id=\"L{}\"><a href=\"#L{}\" style=\"{} text-decoration: none; user-select: none;\">{}</a>  ", line, line, gutter, number)
+        } else {
+            write!(out, "<span id=\"L{}\"><a class=\"pr-gutter\" href=\"#L{}\">{}</a>  ", line, line, number)
+        }
+    }
+    fn token(&mut self, out: &mut dyn Write, _doc: &Document, text: &str, color: &str, style: Option<&Style>) -> io::Result<()> {
+        match style {
+            Some(style) if self.options.inline => write!(out, "<span style=\"{}\">{}</span>", css(style), escape(text)),
+            Some(_) => write!(out, "<span class=\"{}\">{}</span>", css_class(color), escape(text)),
+            None => write!(out, "{}", escape(text)),
+        }
+    }
+    fn line_end(&mut self, out: &mut dyn Write, _doc: &Document, _line: usize) -> io::Result<()> {
+        write!(out, "</span>")
+    }
+    fn skipped(&mut self, out: &mut dyn Write, doc: &Document) -> io::Result<()> {
+        self.row(out)?;
+        if self.options.inline {
+            let gutter = doc.config.color_map.get("nextline").map(css).unwrap_or_default();
+            write!(out, "<span style=\"{} user-select: none;\">...</span>", gutter)
+        } else {
+            write!(out, "<span class=\"pr-gutter\">...</span>")
+        }
+    }
+    fn end(&mut self, out: &mut dyn Write, _doc: &Document) -> io::Result<()> {
+        writeln!(out, "</code></pre>")?;
+        if !self.options.fragment {
+            writeln!(out, "</body>\n</html>")?;
+        }
+        Ok(())
+    }
+}
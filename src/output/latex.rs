@@ -0,0 +1,109 @@
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+use crate::style::Style;
+use crate::terminal::rgb_of;
+use super::{Document, Renderer};
+
+/// xcolor names may only have letters.
+fn color_name(color: colored::Color) -> String {
+    let (r, g, b) = rgb_of(color);
+    let letter = |c: u8| [ (b'a' + c / 16) as char, (b'a' + c % 16) as char ];
+    let mut res = String::from("pr");
+    for c in [ r, g, b ] {
+        res.extend(letter(c));
+    }
+    res
+}
+
+/// Escapes the characters `commandchars=\\\{\}` gives a meaning to.
+fn escape(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\\' => res.push_str("\\textbackslash{}"),
+            '{' => res.push_str("\\{"),
+            '}' => res.push_str("\\}"),
+            _ => res.push(ch),
+        }
+    }
+    res
+}
+
+fn styled(text: &str, style: &Style) -> String {
+    let mut res = escape(text);
+    if let Some(color) = style.foreground {
+        res = format!("\\textcolor{{{}}}{{{}}}", color_name(color), res);
+    }
+    if style.bold {
+        res = format!("\\textbf{{{}}}", res);
+    }
+    if style.italic {
+        res = format!("\\textit{{{}}}", res);
+    }
+    if style.underline {
+        res = format!("\\underline{{{}}}", res);
+    }
+    if style.strikethrough {
+        res = format!("\\sout{{{}}}", res);
+    }
+    if let Some(color) = style.background {
+        res = format!("\\colorbox{{{}}}{{{}}}", color_name(color), res);
+    }
+    res
+}
+
+/// A fancyvrb `Verbatim` block with xcolor colors, ready to `\input`.
+/// Every run of consecutive lines gets its own block, so that `firstnumber` keeps the line numbers.
+#[derive(Default)]
+pub struct LatexRenderer {
+    next_line: Option<usize>,
+}
+
+impl Renderer for LatexRenderer {
+    fn start(&mut self, out: &mut dyn Write, doc: &Document) -> io::Result<()> {
+        // every color of the palette is defined, the tokens aren't known yet
+        let colors: BTreeSet<String> = doc.config.color_map.values()
+            .flat_map(|style| [ style.foreground, style.background ])
+            .flatten()
+            .map(|color| {
+                let (r, g, b) = rgb_of(color);
+                format!("\\definecolor{{{}}}{{RGB}}{{{},{},{}}}", color_name(color), r, g, b)
+            })
+            .collect();
+        writeln!(out, "% {}", doc.title)?;
+        writeln!(out, "% needs \\usepackage{{xcolor}}, \\usepackage{{fancyvrb}} and \\usepackage[normalem]{{ulem}}")?;
+        for color in colors {
+            writeln!(out, "{}", color)?;
+        }
+        Ok(())
+    }
+    fn line_start(&mut self, out: &mut dyn Write, _doc: &Document, line: usize) -> io::Result<()> {
+        if self.next_line != Some(line) {
+            if self.next_line.is_some() {
+                writeln!(out, "\\end{{Verbatim}}")?;
+            }
+            writeln!(out, "\\begin{{Verbatim}}[commandchars=\\\\\\{{\\}},numbers=left,firstnumber={}]", line)?;
+        }
+        self.next_line = Some(line + 1);
+        Ok(())
+    }
+    fn token(&mut self, out: &mut dyn Write, _doc: &Document, text: &str, _color: &str, style: Option<&Style>) -> io::Result<()> {
+        match style {
+            Some(style) => write!(out, "{}", styled(text, style)),
+            None => write!(out, "{}", escape(text)),
+        }
+    }
+    fn line_end(&mut self, out: &mut dyn Write, _doc: &Document, _line: usize) -> io::Result<()> {
+        writeln!(out)
+    }
+    fn skipped(&mut self, _out: &mut dyn Write, _doc: &Document) -> io::Result<()> {
+        // the next line starts a block of its own anyway
+        Ok(())
+    }
+    fn end(&mut self, out: &mut dyn Write, _doc: &Document) -> io::Result<()> {
+        if self.next_line.is_some() {
+            writeln!(out, "\\end{{Verbatim}}")?;
+        }
+        Ok(())
+    }
+}
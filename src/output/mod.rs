@@ -0,0 +1,429 @@
+use std::collections::HashSet;
+use std::io::{self, Write};
+use colored::Color;
+use crate::app::Token;
+use crate::config::Config;
+use crate::logger::Logger;
+use crate::style::Style;
+use crate::terminal::rgb_of;
+use self::ansi::{AnsiOptions, AnsiRenderer};
+use self::html::{HtmlOptions, HtmlRenderer};
+use self::latex::LatexRenderer;
+use self::rtf::RtfRenderer;
+use self::svg::{SvgOptions, SvgRenderer};
+
+pub mod ansi;
+pub mod html;
+pub mod latex;
+pub mod rtf;
+pub mod svg;
+pub mod tokens;
+
+/// The tab stops when `--tab-width` isn't given.
+pub static DEFAULT_TAB_WIDTH: usize = 4;
+
+/// `--output terminal|html|svg|latex|rtf`
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum OutputFormat {
+    Terminal,
+    Html,
+    Svg,
+    Latex,
+    Rtf,
+}
+
+impl OutputFormat {
+    pub fn parse(format: &str) -> Option<OutputFormat> {
+        match format {
+            "terminal" | "ansi" => Some(OutputFormat::Terminal),
+            "html" => Some(OutputFormat::Html),
+            "svg" => Some(OutputFormat::Svg),
+            "latex" | "tex" => Some(OutputFormat::Latex),
+            "rtf" => Some(OutputFormat::Rtf),
+            _ => None,
+        }
+    }
+    /// A whole document with one root, two of them in a row can't be opened as a file.
+    pub fn is_document(&self, options: &OutputOptions) -> bool {
+        match self {
+            OutputFormat::Html => !options.html.fragment,
+            OutputFormat::Svg | OutputFormat::Rtf => true,
+            OutputFormat::Terminal | OutputFormat::Latex => false,
+        }
+    }
+}
+
+/// Replaces everything but ASCII letters, digits, `-` and `_`, so that a color name is a valid CSS class.
+pub fn css_class(color: &str) -> String {
+    let name: String = color.chars().map(|ch| if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' { ch } else { '-' }).collect();
+    format!("pr-{}", name)
+}
+
+/// `#rrggbb`, named colors use the xterm defaults.
+pub fn hex(color: Color) -> String {
+    let (r, g, b) = rgb_of(color);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Escapes text for HTML and XML, `\r` is dropped since the lines are split already.
+/// XML 1.0 forbids the other controls, they are written like `cat -v` does, such as `^[` for ESC.
+pub fn escape(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '&' => res.push_str("&amp;"),
+            '"' => res.push_str("&quot;"),
+            '\r' => {},
+            '\t' | '\n' => res.push(ch),
+            '\0'..='\x1F' => {
+                res.push('^');
+                res.push((ch as u8 + b'@') as char);
+            },
+            _ => res.push(ch),
+        }
+    }
+    res
+}
+
+/// What a renderer knows about the file besides its tokens.
+pub struct Document<'a> {
+    pub title: &'a str,
+    pub lang: &'a str,
+    pub version: &'a str,
+    /// The last line that is printed, for the gutter width.
+    pub lines: usize,
+    pub ranges: &'a LineRanges,
+    /// `--highlight-line`, empty for none.
+    pub highlights: &'a LineRanges,
+    /// The byte ranges (end exclusive) `--grep` matched, in order.
+    pub matches: &'a [(usize, usize)],
+    /// Tabs reach the next multiple of this column.
+    pub tab_width: usize,
+    /// `--show-whitespace`
+    pub show_whitespace: bool,
+    /// The size of the file in bytes.
+    pub size: usize,
+    pub encoding: &'a str,
+    /// `LF`, `CRLF`, `mixed` or `none`.
+    pub line_endings: &'a str,
+    pub config: &'a Config,
+}
+
+/// `--lines 120:180,200:`, the 1-based lines to print, both ends inclusive. No range at all means every line.
+#[derive(Clone, Default, Debug)]
+pub struct LineRanges(Vec<(usize, Option<usize>)>);
+
+impl LineRanges {
+    /// Adds the comma separated ranges in `ranges`, each one is `N`, `N:M`, `N:` or `:M`.
+    pub fn parse(&mut self, ranges: &str) -> Result<(), String> {
+        for range in ranges.split(',') {
+            let error = || format!("Unknown line range `{}`, expect `N`, `N:M`, `N:` or `:M`.", range);
+            let number = |n: &str| n.trim().parse::<usize>().ok().filter(|n| *n > 0).ok_or_else(error);
+            let (start, end) = match range.split_once(':') {
+                Some((start, end)) => (
+                    if start.trim().is_empty() { 1 } else { number(start)? },
+                    if end.trim().is_empty() { None } else { Some(number(end)?) },
+                ),
+                None => {
+                    let line = number(range)?;
+                    (line, Some(line))
+                },
+            };
+            if end.is_some_and(|end| end < start) {
+                return Err(format!("Line range `{}` ends before it starts.", range))
+            }
+            self.0.push((start, end));
+        }
+        Ok(())
+    }
+    /// Adds the lines from `start` to `end`, both inclusive.
+    pub fn push(&mut self, start: usize, end: usize) {
+        self.0.push((start, Some(end)));
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn contains(&self, line: usize) -> bool {
+        self.0.is_empty() || self.0.iter().any(|(start, end)| *start <= line && end.is_none_or(|end| line <= end))
+    }
+    /// The last line of a file with `lines` lines that is printed.
+    pub fn last(&self, lines: usize) -> usize {
+        if self.0.is_empty() {
+            return lines
+        }
+        self.0.iter()
+            .filter(|(start, _)| *start <= lines)
+            .map(|(_, end)| end.map_or(lines, |end| end.min(lines)))
+            .max()
+            .unwrap_or(lines)
+    }
+}
+
+/// One output format. `render` calls the hooks in order, a line at a time:
+/// `start`, then `line_start`, `token`s and `line_end` for each line, then `end`.
+/// The text of a token has its tabs expanded and no `\r` left.
+pub trait Renderer {
+    fn start(&mut self, out: &mut dyn Write, doc: &Document) -> io::Result<()>;
+    fn line_start(&mut self, out: &mut dyn Write, doc: &Document, line: usize) -> io::Result<()>;
+    /// `style` is `None` for plain text, `color` is the name the grammar gave the token.
+    fn token(&mut self, out: &mut dyn Write, doc: &Document, text: &str, color: &str, style: Option<&Style>) -> io::Result<()>;
+    fn line_end(&mut self, out: &mut dyn Write, doc: &Document, line: usize) -> io::Result<()>;
+    /// Between two printed lines that aren't next to each other.
+    fn skipped(&mut self, out: &mut dyn Write, doc: &Document) -> io::Result<()>;
+    fn end(&mut self, out: &mut dyn Write, doc: &Document) -> io::Result<()>;
+}
+
+impl OutputFormat {
+    pub fn renderer(&self, options: &OutputOptions) -> Box<dyn Renderer> {
+        match self {
+            OutputFormat::Terminal => Box::new(AnsiRenderer::new(options.ansi)),
+            OutputFormat::Html => Box::new(HtmlRenderer::new(options.html)),
+            OutputFormat::Svg => Box::new(SvgRenderer::new(options.svg)),
+            OutputFormat::Latex => Box::new(LatexRenderer::default()),
+            OutputFormat::Rtf => Box::new(RtfRenderer::default()),
+        }
+    }
+}
+
+/// The options of every format, only the chosen one reads its own.
+#[derive(Copy, Clone, Default)]
+pub struct OutputOptions {
+    pub ansi: AnsiOptions,
+    pub html: HtmlOptions,
+    pub svg: SvgOptions,
+}
+
+/// Feeds the tokens of the lines in `Document::ranges` to `renderer` line by line and resolves their colors.
+/// A color the theme doesn't define is printed as plain text, with one warning per scope.
+pub fn render(renderer: &mut dyn Renderer, out: &mut dyn Write, doc: &Document, tokens: &[Token], logger: &mut Logger) -> io::Result<()> {
+    let mut unresolved = HashSet::new();
+    let mut line = 1;
+    let mut column = 0;
+    let mut shown = doc.ranges.contains(line);
+    let mut last_shown = None;
+    renderer.start(out, doc)?;
+    if shown {
+        renderer.line_start(out, doc, line)?;
+    }
+    for (i, token) in tokens.iter().enumerate() {
+        match token.color_name() {
+            "nextline" => {
+                if shown {
+                    renderer.line_end(out, doc, line)?;
+                    last_shown = Some(line);
+                }
+                line += 1;
+                column = 0;
+                shown = doc.ranges.contains(line);
+                if shown {
+                    if last_shown.is_some_and(|last| last + 1 < line) {
+                        renderer.skipped(out, doc)?;
+                    }
+                    renderer.line_start(out, doc, line)?;
+                }
+            },
+            // the tokens before the range are highlighted too, so that a note opened above it is still a note
+            _ if !shown => {},
+            _ if doc.show_whitespace && token.as_str().chars().all(is_blank) => {
+                let trailing = tokens.get(i + 1).is_none_or(|next| next.color_name() == "nextline");
+                let color = if trailing { "trailing_whitespace" } else { "whitespace" };
+                render_token(renderer, out, doc, token, color, doc.config.color_map.get(color), &mut column)?;
+            },
+            color @ ("default" | "unknown") => render_token(renderer, out, doc, token, color, None, &mut column)?,
+            color => {
+                let style = doc.config.color_map.get(color);
+                if style.is_none() && unresolved.insert(token.scope().clone()) {
+                    logger.warn(format!("Grammar `{}`: scope `{}` maps to the unknown color `{}`, it is printed as plain text.", doc.lang, token.scope(), color));
+                }
+                render_token(renderer, out, doc, token, color, style, &mut column)?;
+            },
+        }
+    }
+    if shown {
+        renderer.line_end(out, doc, line)?;
+    }
+    renderer.end(out, doc)
+}
+
+/// Splits the token at the `--grep` matches, which are drawn as `grep_match` with inverted colors.
+fn render_token(renderer: &mut dyn Renderer, out: &mut dyn Write, doc: &Document, token: &Token, color: &str, style: Option<&Style>, column: &mut usize) -> io::Result<()> {
+    let text = token.as_str();
+    let (begin, end) = token.range();
+    let end = end + 1;
+    if text.len() != end - begin {
+        // an `invalid` token reads `\xNN`, its text doesn't line up with the bytes
+        return renderer.token(out, doc, &expand(text, column, doc), color, style)
+    }
+    let mut pos = 0;
+    let first = doc.matches.partition_point(|(_, stop)| *stop <= begin);
+    for (start, stop) in doc.matches[first..].iter().take_while(|(start, _)| *start < end) {
+        let (mut start, mut stop) = (start.max(&begin) - begin, stop.min(&end) - begin);
+        // a byte pattern such as `(?-u:\xC3)` can stop inside a character, the whole character is drawn as matched
+        while !text.is_char_boundary(start) {
+            start -= 1;
+        }
+        while !text.is_char_boundary(stop) {
+            stop += 1;
+        }
+        start = start.max(pos);
+        if start >= stop {
+            continue
+        }
+        if pos < start {
+            renderer.token(out, doc, &expand(&text[pos..start], column, doc), color, style)?;
+        }
+        renderer.token(out, doc, &expand(&text[start..stop], column, doc), "grep_match", Some(&inverted(style, doc.config)))?;
+        pos = stop;
+    }
+    if pos < text.len() {
+        renderer.token(out, doc, &expand(&text[pos..], column, doc), color, style)?;
+    }
+    Ok(())
+}
+
+/// Swaps the colors of `style`, the page colors stand in for the ones it doesn't have.
+pub fn inverted(style: Option<&Style>, config: &Config) -> Style {
+    let page = config.color_map.get("background").copied().unwrap_or_default();
+    let style = style.copied().unwrap_or_default();
+    Style {
+        foreground: Some(style.background.or(page.background).unwrap_or(Color::Black)),
+        background: Some(style.foreground.or(page.foreground).unwrap_or(Color::White)),
+        ..style
+    }
+}
+
+/// The characters the tokenizer groups into blank tokens.
+fn is_blank(ch: char) -> bool {
+    ch == ' ' || ch == '\t' || ch == '\r'
+}
+
+/// Expands the tabs of `text` to `Document::tab_width` and drops `\r`, `column` is where the text starts on its line.
+/// `--show-whitespace` draws them instead: `·` for a space, `→` for a tab and `␍` for a CR.
+fn expand(text: &str, column: &mut usize, doc: &Document) -> String {
+    let mut res = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\t' => {
+                let spaces = doc.tab_width - *column % doc.tab_width;
+                if doc.show_whitespace {
+                    res.push('→');
+                    res.push_str(&" ".repeat(spaces - 1));
+                } else {
+                    res.push_str(&" ".repeat(spaces));
+                }
+                *column += spaces;
+            },
+            '\r' if doc.show_whitespace => {
+                res.push('␍');
+                *column += 1;
+            },
+            '\r' => {},
+            ' ' if doc.show_whitespace => {
+                res.push('·');
+                *column += 1;
+            },
+            _ => {
+                res.push(ch);
+                *column += char_width(ch);
+            },
+        }
+    }
+    res
+}
+
+/// The code points that take two cells in a terminal: Hangul, CJK, fullwidth forms and most emoji.
+static WIDE_CHARS: [ (u32, u32); 17 ] = [
+    (0x1100, 0x115f),
+    (0x2e80, 0x303e),
+    (0x3041, 0x33ff),
+    (0x3400, 0x4dbf),
+    (0x4e00, 0x9fff),
+    (0xa000, 0xa4cf),
+    (0xa960, 0xa97f),
+    (0xac00, 0xd7a3),
+    (0xf900, 0xfaff),
+    (0xfe10, 0xfe19),
+    (0xfe30, 0xfe6f),
+    (0xff00, 0xff60),
+    (0xffe0, 0xffe6),
+    (0x1f300, 0x1f64f),
+    (0x1f900, 0x1f9ff),
+    (0x20000, 0x2fffd),
+    (0x30000, 0x3fffd),
+];
+
+/// The code points that take no cell: combining marks, zero-width spaces and joiners, variation selectors.
+static ZERO_WIDTH_CHARS: [ (u32, u32); 6 ] = [
+    (0x0300, 0x036f),
+    (0x200b, 0x200f),
+    (0x20d0, 0x20ff),
+    (0xfe00, 0xfe0f),
+    (0xfe20, 0xfe2f),
+    (0xe0100, 0xe01ef),
+];
+
+/// How many terminal cells `ch` takes, tabs are up to the caller.
+pub fn char_width(ch: char) -> usize {
+    let code = ch as u32;
+    let within = |ranges: &[(u32, u32)]| ranges.iter().any(|(start, end)| *start <= code && code <= *end);
+    if ch.is_control() || within(&ZERO_WIDTH_CHARS) {
+        0
+    } else if within(&WIDE_CHARS) {
+        2
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::Token;
+    use crate::config::Config;
+    use super::html::{HtmlOptions, HtmlRenderer};
+    use super::{char_width, escape, render_token, Document, LineRanges};
+
+    #[test]
+    fn it_should_parse_line_ranges() {
+        let mut ranges = LineRanges::default();
+        assert!(ranges.contains(7) && ranges.last(40) == 40);
+        ranges.parse("3:5,9").unwrap();
+        ranges.parse("30:").unwrap();
+        assert!(ranges.contains(3) && ranges.contains(5) && ranges.contains(9) && ranges.contains(31));
+        assert!(!ranges.contains(2) && !ranges.contains(6) && !ranges.contains(10));
+        assert_eq!(ranges.last(20), 9);
+        assert_eq!(ranges.last(40), 40);
+        assert!(ranges.parse("0:3").is_err() && ranges.parse("5:3").is_err() && ranges.parse("a").is_err());
+    }
+
+    #[test]
+    fn it_should_escape_for_xml() {
+        assert_eq!(escape("let s = \"\x1B[1m\";\r\n\t<&>"), "let s = &quot;^[[1m&quot;;\n\t&lt;&amp;&gt;");
+        assert_eq!(escape("\0\x07"), "^@^G");
+    }
+
+    #[test]
+    fn it_should_count_display_cells() {
+        assert_eq!(char_width('a'), 1);
+        assert_eq!(char_width('中'), 2);
+        assert_eq!(char_width('한'), 2);
+        assert_eq!(char_width('\u{301}'), 0);
+        assert_eq!(char_width('↪'), 1);
+    }
+
+    #[test]
+    fn it_should_keep_a_grep_match_on_char_boundaries() {
+        let (ranges, config) = (LineRanges::default(), Config::default());
+        // `(?-u:\xC3)` matches the first byte of `é` only
+        let matches = [ (4, 5) ];
+        let doc = Document {
+            title: "u.rs", lang: "rust", version: "", lines: 1, ranges: &ranges, highlights: &ranges, matches: &matches,
+            tab_width: 4, show_whitespace: false, size: 6, encoding: "UTF-8", line_endings: "none", config: &config,
+        };
+        let mut out = Vec::new();
+        let mut renderer = HtmlRenderer::new(HtmlOptions::default());
+        render_token(&mut renderer, &mut out, &doc, &Token::new(String::from("café"), (1, 5)), "symbol", None, &mut 0).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "caf<span class=\"pr-grep_match\">é</span>");
+    }
+}
@@ -0,0 +1,106 @@
+use std::io::{self, Write};
+use colored::Color;
+use crate::style::Style;
+use crate::terminal::rgb_of;
+use super::{Document, Renderer};
+
+/// Escapes the RTF control characters, everything beyond ASCII becomes `\uN?`.
+fn escape(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\\' | '{' | '}' => {
+                res.push('\\');
+                res.push(ch);
+            },
+            ch if ch.is_ascii() => res.push(ch),
+            ch => {
+                // `\u` takes a signed 16-bit number, so the rest of the planes are written as surrogate pairs
+                let mut units = [ 0; 2 ];
+                for unit in ch.encode_utf16(&mut units) {
+                    res.push_str(&format!("\\u{}?", *unit as i16));
+                }
+            },
+        }
+    }
+    res
+}
+
+/// A rich text document, which word processors accept on paste.
+#[derive(Default)]
+pub struct RtfRenderer {
+    /// `\cf0` is the default color, so `\cfN` is the entry `N - 1`.
+    color_table: Vec<(u8, u8, u8)>,
+    max_line_len: usize,
+}
+
+impl RtfRenderer {
+    fn color_index(&self, color: Color) -> usize {
+        let rgb = rgb_of(color);
+        self.color_table.iter().position(|c| *c == rgb).map(|index| index + 1).unwrap_or(0)
+    }
+}
+
+impl RtfRenderer {
+    fn gutter(&self, out: &mut dyn Write, doc: &Document, text: &str) -> io::Result<()> {
+        match doc.config.color_map.get("nextline").and_then(|style| style.foreground) {
+            Some(color) => write!(out, "{{\\cf{} {}}}", self.color_index(color), text),
+            None => write!(out, "{}", text),
+        }
+    }
+}
+
+impl Renderer for RtfRenderer {
+    fn start(&mut self, out: &mut dyn Write, doc: &Document) -> io::Result<()> {
+        // the table comes before the text, so it holds every color of the palette
+        let mut names: Vec<&String> = doc.config.color_map.keys().collect();
+        names.sort();
+        for name in names {
+            let style = &doc.config.color_map[name];
+            for color in [ style.foreground, style.background ].into_iter().flatten() {
+                let rgb = rgb_of(color);
+                if !self.color_table.contains(&rgb) {
+                    self.color_table.push(rgb);
+                }
+            }
+        }
+        self.max_line_len = format!("{}", doc.lines).len();
+        write!(out, "{{\\rtf1\\ansi\\deff0{{\\fonttbl{{\\f0\\fmodern Courier New;}}}}\n{{\\colortbl;")?;
+        for (r, g, b) in &self.color_table {
+            write!(out, "\\red{}\\green{}\\blue{};", r, g, b)?;
+        }
+        writeln!(out, "}}\n\\f0\\fs20")
+    }
+    fn line_start(&mut self, out: &mut dyn Write, doc: &Document, line: usize) -> io::Result<()> {
+        self.gutter(out, doc, &format!("{:<width$}  ", line, width = self.max_line_len))
+    }
+    fn token(&mut self, out: &mut dyn Write, _doc: &Document, text: &str, _color: &str, style: Option<&Style>) -> io::Result<()> {
+        let style = match style {
+            Some(style) => style,
+            None => return write!(out, "{}", escape(text)),
+        };
+        let mut control = String::new();
+        if let Some(color) = style.foreground {
+            control.push_str(&format!("\\cf{}", self.color_index(color)));
+        }
+        if let Some(color) = style.background {
+            control.push_str(&format!("\\highlight{}", self.color_index(color)));
+        }
+        for (flag, word) in [ (style.bold, "\\b"), (style.italic, "\\i"), (style.underline, "\\ul"), (style.strikethrough, "\\strike") ] {
+            if flag {
+                control.push_str(word);
+            }
+        }
+        write!(out, "{{{} {}}}", control, escape(text))
+    }
+    fn line_end(&mut self, out: &mut dyn Write, _doc: &Document, _line: usize) -> io::Result<()> {
+        writeln!(out, "\\line")
+    }
+    fn skipped(&mut self, out: &mut dyn Write, doc: &Document) -> io::Result<()> {
+        self.gutter(out, doc, "...")?;
+        writeln!(out, "\\line")
+    }
+    fn end(&mut self, out: &mut dyn Write, _doc: &Document) -> io::Result<()> {
+        writeln!(out, "}}")
+    }
+}
@@ -0,0 +1,142 @@
+use std::io::{self, Write};
+use crate::style::Style;
+use super::{char_width, escape, hex, Document, Renderer};
+
+static FONT_SIZE: f32 = 14.0;
+static LINE_HEIGHT: f32 = 20.0;
+/// The advance of a monospace glyph is about 0.6em.
+static CHAR_WIDTH: f32 = FONT_SIZE * 0.6;
+static PADDING: f32 = 16.0;
+static FRAME_HEIGHT: f32 = 36.0;
+
+#[derive(Copy, Clone, Default)]
+pub struct SvgOptions {
+    /// A window title bar with the three buttons around the code.
+    pub frame: bool,
+}
+
+/// The cells `text` takes in the monospace grid, a CJK character takes two like in the terminal.
+/// A control other than `\r` is escaped to two characters such as `^[`.
+fn columns(text: &str) -> usize {
+    text.chars().map(|ch| match ch {
+        '\r' => 0,
+        '\0'..='\x1F' => 2,
+        ch => char_width(ch),
+    }).sum()
+}
+
+/// The presentation attributes of a style, the background is drawn as a `<rect>` instead.
+fn attributes(style: &Style) -> String {
+    let mut res = String::new();
+    if let Some(color) = style.foreground {
+        res.push_str(&format!(" fill=\"{}\"", hex(color)));
+    }
+    if style.bold {
+        res.push_str(" font-weight=\"bold\"");
+    }
+    if style.italic {
+        res.push_str(" font-style=\"italic\"");
+    }
+    if style.underline || style.strikethrough {
+        let lines: Vec<&str> = [ (style.underline, "underline"), (style.strikethrough, "line-through") ].iter()
+            .filter(|(flag, _)| *flag)
+            .map(|(_, line)| *line)
+            .collect();
+        res.push_str(&format!(" text-decoration=\"{}\"", lines.join(" ")));
+    }
+    if style.dim {
+        res.push_str(" opacity=\"0.6\"");
+    }
+    res
+}
+
+/// The `(text, style)` pieces of a line.
+type Line = Vec<(String, Option<Style>)>;
+
+/// An SVG image, like a screenshot of the code.
+/// The size depends on the longest line, so the lines are kept until `end`.
+pub struct SvgRenderer {
+    options: SvgOptions,
+    /// The line number and the `(text, style)` pieces of each line, `None` for the `...` between two ranges.
+    lines: Vec<(Option<usize>, Line)>,
+}
+
+impl SvgRenderer {
+    pub fn new(options: SvgOptions) -> SvgRenderer {
+        SvgRenderer { options, lines: Vec::new() }
+    }
+}
+
+impl Renderer for SvgRenderer {
+    fn start(&mut self, _out: &mut dyn Write, _doc: &Document) -> io::Result<()> {
+        Ok(())
+    }
+    fn line_start(&mut self, _out: &mut dyn Write, _doc: &Document, line: usize) -> io::Result<()> {
+        self.lines.push((Some(line), Vec::new()));
+        Ok(())
+    }
+    fn token(&mut self, _out: &mut dyn Write, _doc: &Document, text: &str, _color: &str, style: Option<&Style>) -> io::Result<()> {
+        if let Some((_, line)) = self.lines.last_mut() {
+            line.push((text.to_string(), style.copied()));
+        }
+        Ok(())
+    }
+    fn line_end(&mut self, _out: &mut dyn Write, _doc: &Document, _line: usize) -> io::Result<()> {
+        Ok(())
+    }
+    fn skipped(&mut self, _out: &mut dyn Write, _doc: &Document) -> io::Result<()> {
+        self.lines.push((None, Vec::new()));
+        Ok(())
+    }
+    fn end(&mut self, out: &mut dyn Write, doc: &Document) -> io::Result<()> {
+        let lines = &self.lines;
+        let config = doc.config;
+        let options = &self.options;
+        let title = doc.title;
+        let gutter_len = format!("{}", doc.lines).len() + 2;
+        let max_columns = lines.iter()
+            .map(|(_, line)| line.iter().map(|(text, _)| columns(text)).sum::<usize>())
+            .max()
+            .unwrap_or(0);
+        let top = if options.frame { FRAME_HEIGHT } else { 0.0 };
+        let width = PADDING * 2.0 + (gutter_len + max_columns) as f32 * CHAR_WIDTH;
+        let height = top + PADDING * 2.0 + lines.len() as f32 * LINE_HEIGHT;
+        let page = config.color_map.get("background").copied().unwrap_or_default();
+        let background = page.background.map(hex).unwrap_or(String::from("#1e1e1e"));
+        let foreground = page.foreground.map(hex).unwrap_or(String::from("#dcdcdc"));
+        let gutter = config.color_map.get("nextline").map(attributes).unwrap_or_default();
+        writeln!(out, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.1}\" height=\"{:.1}\" viewBox=\"0 0 {:.1} {:.1}\">", width, height, width, height)?;
+        writeln!(out, "<title>{}</title>", escape(title))?;
+        writeln!(out, "<rect width=\"100%\" height=\"100%\" rx=\"{}\" fill=\"{}\"/>", if options.frame { 8 } else { 0 }, background)?;
+        if options.frame {
+            for (i, color) in [ "#ff5f56", "#ffbd2e", "#27c93f" ].iter().enumerate() {
+                writeln!(out, "<circle cx=\"{}\" cy=\"{}\" r=\"6\" fill=\"{}\"/>", PADDING + 4.0 + i as f32 * 20.0, FRAME_HEIGHT / 2.0, color)?;
+            }
+            writeln!(out, "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\" font-family=\"sans-serif\" font-size=\"13\" fill=\"{}\" opacity=\"0.7\">{}</text>", width / 2.0, FRAME_HEIGHT / 2.0 + 4.0, foreground, escape(title))?;
+        }
+        writeln!(out, "<g font-family=\"ui-monospace, SFMono-Regular, Menlo, Consolas, monospace\" font-size=\"{}\" fill=\"{}\">", FONT_SIZE, foreground)?;
+        for (i, (number, line)) in lines.iter().enumerate() {
+            let y = top + PADDING + (i as f32 + 0.75) * LINE_HEIGHT;
+            let mut x = PADDING + gutter_len as f32 * CHAR_WIDTH;
+            let mut rects = String::new();
+            let mut spans = String::new();
+            for (text, style) in line {
+                let len = columns(text) as f32;
+                match style {
+                    Some(style) => {
+                        if let Some(color) = style.background {
+                            rects.push_str(&format!("<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{}\" fill=\"{}\"/>\n", x, y - LINE_HEIGHT * 0.75, len * CHAR_WIDTH, LINE_HEIGHT, hex(color)));
+                        }
+                        spans.push_str(&format!("<tspan{}>{}</tspan>", attributes(style), escape(text)));
+                    },
+                    None => spans.push_str(&escape(text)),
+                }
+                x += len * CHAR_WIDTH;
+            }
+            out.write_all(rects.as_bytes())?;
+            writeln!(out, "<text x=\"{:.1}\" y=\"{:.1}\"{}>{}</text>", PADDING, y, gutter, number.map_or(String::from("..."), |number| number.to_string()))?;
+            writeln!(out, "<text x=\"{:.1}\" y=\"{:.1}\" xml:space=\"preserve\">{}</text>", PADDING + gutter_len as f32 * CHAR_WIDTH, y, spans)?;
+        }
+        writeln!(out, "</g>\n</svg>")
+    }
+}
@@ -0,0 +1,121 @@
+use std::io::{self, Write};
+use serde_json::{json, Value};
+use crate::app::Token;
+use super::Document;
+
+/// `--emit tokens-json|tokens-jsonl`
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Emit {
+    /// One document with the tokens in an array, for a single file.
+    TokensJson,
+    /// One token per line, every line names its file.
+    TokensJsonl,
+}
+
+impl Emit {
+    pub fn parse(emit: &str) -> Option<Emit> {
+        match emit {
+            "tokens-json" => Some(Emit::TokensJson),
+            "tokens-jsonl" => Some(Emit::TokensJsonl),
+            _ => None,
+        }
+    }
+}
+
+/// Every token of the lines in `Document::ranges` with its byte range (end exclusive), 1-based line and column (in characters),
+/// the scope and color name the grammar gave it and the style the theme resolves that color to.
+/// An `invalid` token is 1 column like in the tokenizer diagnostic, however many `\xNN` it reads.
+fn token_values(doc: &Document, tokens: &[Token]) -> Vec<Value> {
+    let mut values = Vec::with_capacity(tokens.len());
+    let (mut line, mut column) = (1, 1);
+    for token in tokens {
+        let position = (line, column);
+        if token.color_name() == "nextline" {
+            line += 1;
+            column = 1;
+        } else if token.color_name() == "invalid" {
+            column += 1;
+        } else {
+            column += token.as_str().chars().count();
+        }
+        if !doc.ranges.contains(position.0) {
+            continue
+        }
+        let (begin, end) = token.range();
+        let style = doc.config.color_map.get(token.color_name()).map(|style| style.to_json());
+        values.push(json!({
+            "text": token.as_str(),
+            "range": [ begin, end + 1 ],
+            "line": position.0,
+            "column": position.1,
+            "scope": token.scope().as_str(),
+            "color": token.color_name(),
+            "style": style,
+        }));
+    }
+    values
+}
+
+pub fn emit(emit: Emit, out: &mut dyn Write, doc: &Document, tokens: &[Token]) -> io::Result<()> {
+    let values = token_values(doc, tokens);
+    match emit {
+        Emit::TokensJson => {
+            let document = json!({
+                "file": doc.title,
+                "language": doc.lang,
+                "tokens": values,
+            });
+            serde_json::to_writer_pretty(&mut *out, &document)?;
+            writeln!(out)
+        },
+        Emit::TokensJsonl => {
+            for mut value in values {
+                value["file"] = Value::from(doc.title);
+                serde_json::to_writer(&mut *out, &value)?;
+                writeln!(out)?;
+            }
+            Ok(())
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use serde_json::json;
+    use crate::app::Token;
+    use crate::config::Config;
+    use super::super::{Document, LineRanges};
+    use super::token_values;
+
+    #[test]
+    fn it_should_give_every_token_its_range_position_and_style() {
+        let (ranges, config) = (LineRanges::default(), Config::default());
+        let doc = Document {
+            title: "a.rs", lang: "rust", version: "", lines: 2, ranges: &ranges, highlights: &ranges, matches: &[],
+            tab_width: 4, show_whitespace: false, size: 11, encoding: "UTF-8", line_endings: "LF", config: &config,
+        };
+        let colored = |text: &str, range, scope: &str, color: &str| {
+            let mut token = Token::new(String::from(text), range);
+            token.color(Rc::new(String::from(scope)), Rc::new(String::from(color)));
+            token
+        };
+        let tokens = [
+            colored("fn", (0, 1), "keyword.fn", "keyword"),
+            colored("\n", (2, 2), "nextline", "nextline"),
+            colored("\\xFF", (3, 3), "invalid", "invalid"),
+            colored("é", (4, 5), "name", "no_such_color"),
+            Token::new(String::from(" x"), (6, 7)),
+        ];
+        let values = token_values(&doc, &tokens);
+        let position = |i: usize| (values[i]["range"].clone(), values[i]["line"].clone(), values[i]["column"].clone());
+        assert_eq!(position(0), (json!([0, 2]), json!(1), json!(1)));
+        assert_eq!(position(2), (json!([3, 4]), json!(2), json!(1)));
+        assert_eq!(position(3), (json!([4, 6]), json!(2), json!(2)));
+        assert_eq!(position(4), (json!([6, 8]), json!(2), json!(3)));
+        assert_eq!(values[0]["style"], config.color_map["keyword"].to_json());
+        assert_eq!(values[3]["style"], json!(null));
+        assert_eq!(values[4]["style"], json!(null));
+        assert_eq!((values[3]["scope"].clone(), values[3]["color"].clone()), (json!("name"), json!("no_such_color")));
+    }
+}
@@ -0,0 +1,139 @@
+use serde_json::{Map, Number, Value};
+
+/// A tiny XML property list reader, just enough for `.tmTheme` files.
+/// `<dict>` becomes an object, `<array>` an array and the scalars their JSON counterpart.
+pub struct PlistParser<'a> {
+    src: &'a str,
+    curr: usize,
+}
+
+impl<'a> PlistParser<'a> {
+    pub fn new(src: &'a str) -> PlistParser<'a> {
+        PlistParser { src, curr: 0 }
+    }
+    pub fn parse(&mut self) -> Result<Value, String> {
+        loop {
+            let tag = self.next_tag()?.ok_or_else(|| String::from("Plist Error: Unexpected EOF."))?;
+            match tag.as_str() {
+                "plist" => continue,
+                _ => return self.value(&tag),
+            }
+        }
+    }
+    /// Skips text, `<?...?>`, `<!...>` and comments, returns the name of the next tag.
+    /// Closing tags are returned as `/name`, empty ones as `name/`.
+    fn next_tag(&mut self) -> Result<Option<String>, String> {
+        loop {
+            let rest = &self.src[self.curr..];
+            let begin = match rest.find('<') {
+                Some(begin) => begin,
+                None => return Ok(None),
+            };
+            let rest = &rest[begin..];
+            let (skip, end) = if rest.starts_with("<!--") {
+                (true, "-->")
+            } else if rest.starts_with("<?") || rest.starts_with("<!") {
+                (true, ">")
+            } else {
+                (false, ">")
+            };
+            let len = rest.find(end).ok_or_else(|| String::from("Plist Error: Unclosed tag."))?;
+            self.curr += begin + len + end.len();
+            if skip {
+                continue
+            }
+            let tag = rest[1..len].trim();
+            // attributes such as `version="1.0"` are not interesting
+            let name = tag.split_whitespace().next().unwrap_or("");
+            return Ok(Some(if tag.ends_with('/') && !name.ends_with('/') {
+                format!("{}/", name)
+            } else {
+                name.to_string()
+            }))
+        }
+    }
+    fn text(&mut self, tag: &str) -> Result<String, String> {
+        let close = format!("</{}>", tag);
+        let rest = &self.src[self.curr..];
+        let len = rest.find(&close).ok_or_else(|| format!("Plist Error: Expect `{}`.", close))?;
+        self.curr += len + close.len();
+        Ok(unescape(&rest[..len]))
+    }
+    fn value(&mut self, tag: &str) -> Result<Value, String> {
+        match tag {
+            "dict" => {
+                let mut object = Map::new();
+                loop {
+                    match self.next_tag()?.as_deref() {
+                        Some("/dict") => return Ok(Value::Object(object)),
+                        Some("key") => {
+                            let key = self.text("key")?;
+                            let tag = self.next_tag()?.ok_or_else(|| String::from("Plist Error: Unexpected EOF."))?;
+                            object.insert(key, self.value(&tag)?);
+                        },
+                        Some(tag) => return Err(format!("Plist Error: Expect `<key>` but found `<{}>`.", tag)),
+                        None => return Err(String::from("Plist Error: Unexpected EOF.")),
+                    }
+                }
+            },
+            "array" => {
+                let mut array = Vec::new();
+                loop {
+                    match self.next_tag()? {
+                        Some(tag) if tag == "/array" => return Ok(Value::Array(array)),
+                        Some(tag) => array.push(self.value(&tag)?),
+                        None => return Err(String::from("Plist Error: Unexpected EOF.")),
+                    }
+                }
+            },
+            "dict/" => Ok(Value::Object(Map::new())),
+            "array/" => Ok(Value::Array(Vec::new())),
+            "string/" => Ok(Value::String(String::new())),
+            "true/" => Ok(Value::Bool(true)),
+            "false/" => Ok(Value::Bool(false)),
+            "string" | "date" | "data" => Ok(Value::String(self.text(tag)?)),
+            "integer" | "real" => {
+                let text = self.text(tag)?;
+                Ok(text.trim().parse::<f64>().ok().and_then(Number::from_f64).map(Value::Number).unwrap_or(Value::Null))
+            },
+            _ => Err(format!("Plist Error: Unexpected tag `<{}>`.", tag)),
+        }
+    }
+}
+
+fn unescape(text: &str) -> String {
+    let mut res = String::new();
+    let mut rest = text;
+    while let Some(begin) = rest.find('&') {
+        res.push_str(&rest[..begin]);
+        rest = &rest[begin..];
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => break,
+        };
+        let entity = &rest[1..end];
+        let ch = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity.strip_prefix("#x").map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(|dec| dec.parse::<u32>()))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32),
+        };
+        match ch {
+            Some(ch) => {
+                res.push(ch);
+                rest = &rest[end + 1..];
+            },
+            None => {
+                res.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    res.push_str(rest);
+    res
+}
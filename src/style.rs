@@ -0,0 +1,111 @@
+use colored::Color;
+use serde_json::{Map, Value};
+use crate::config::{Rgb, parse_rgb};
+use crate::terminal::ColorSupport;
+
+/// How a scope is painted, every attribute is optional.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Style {
+    pub foreground: Option<Color>,
+    pub background: Option<Color>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub dim: bool,
+    pub strikethrough: bool,
+}
+
+/// Parses `"#rrggbb"`, `[r, g, b]` or an ANSI color name such as `"bright_black"`.
+pub fn parse_color(value: &Value) -> Option<Color> {
+    if let Some((r, g, b)) = parse_rgb(value) {
+        return Some(Color::TrueColor { r, g, b })
+    }
+    value.as_str()?.replace('_', " ").parse::<Color>().ok()
+}
+
+impl Style {
+    pub fn foreground(rgb: Rgb) -> Style {
+        Style { foreground: Some(Color::TrueColor { r: rgb.0, g: rgb.1, b: rgb.2 }), ..Style::default() }
+    }
+    /// A style is either a plain color (the foreground) or an object like
+    /// `{ "foreground": "#ff6b6b", "background": "#282c34", "bold": true, "italic": true }`.
+    pub fn parse(value: &Value) -> Result<Style, String> {
+        let object = match value.as_object() {
+            Some(object) => object,
+            None => return parse_color(value)
+                .map(|color| Style { foreground: Some(color), ..Style::default() })
+                .ok_or_else(|| format!("Invalid color `{}`", value)),
+        };
+        let mut style = Style::default();
+        for (key, value) in object {
+            match key.as_str() {
+                "foreground" | "background" => {
+                    let color = parse_color(value).ok_or_else(|| format!("Invalid color `{}` for `{}`", value, key))?;
+                    if key == "foreground" {
+                        style.foreground = Some(color);
+                    } else {
+                        style.background = Some(color);
+                    }
+                },
+                "bold" | "italic" | "underline" | "dim" | "strikethrough" => {
+                    let flag = value.as_bool().ok_or_else(|| format!("`{}` should be true or false", key))?;
+                    match key.as_str() {
+                        "bold" => style.bold = flag,
+                        "italic" => style.italic = flag,
+                        "underline" => style.underline = flag,
+                        "dim" => style.dim = flag,
+                        _ => style.strikethrough = flag,
+                    }
+                },
+                _ => return Err(format!("Unknown style attribute `{}`", key)),
+            }
+        }
+        Ok(style)
+    }
+    /// The inverse of `Style::parse`, a plain color when only the foreground is set.
+    pub fn to_json(self) -> Value {
+        let color = |color: Color| match color {
+            Color::TrueColor { r, g, b } => Value::String(format!("#{:02x}{:02x}{:02x}", r, g, b)),
+            color => Value::String(format!("{:?}", color).replace("Bright", "bright_").to_lowercase()),
+        };
+        let only_foreground = Style { foreground: self.foreground, ..Style::default() };
+        if let (Some(foreground), true) = (self.foreground, self == only_foreground) {
+            return color(foreground)
+        }
+        let mut object = Map::new();
+        if let Some(foreground) = self.foreground {
+            object.insert(String::from("foreground"), color(foreground));
+        }
+        if let Some(background) = self.background {
+            object.insert(String::from("background"), color(background));
+        }
+        for (flag, name) in [ (self.bold, "bold"), (self.italic, "italic"), (self.underline, "underline"), (self.dim, "dim"), (self.strikethrough, "strikethrough") ] {
+            if flag {
+                object.insert(String::from(name), Value::Bool(true));
+            }
+        }
+        Value::Object(object)
+    }
+    /// Wraps `text` in the SGR escapes of this style, quantized to what the output supports.
+    pub fn paint(&self, text: &str, support: ColorSupport) -> String {
+        if support == ColorSupport::None {
+            return text.to_string()
+        }
+        let mut params = Vec::new();
+        for (flag, param) in [ (self.bold, "1"), (self.dim, "2"), (self.italic, "3"), (self.underline, "4"), (self.strikethrough, "9") ] {
+            if flag {
+                params.push(param.to_string());
+            }
+        }
+        if let Some(color) = self.foreground.and_then(|color| support.sgr(color, false)) {
+            params.push(color);
+        }
+        if let Some(color) = self.background.and_then(|color| support.sgr(color, true)) {
+            params.push(color);
+        }
+        if params.is_empty() {
+            return text.to_string()
+        }
+        format!("\x1b[{}m{}\x1b[0m", params.join(";"), text)
+    }
+}
@@ -0,0 +1,201 @@
+use std::env;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, IsTerminal};
+use std::process::{Command, Stdio};
+use colored::Color;
+
+/// `--color=auto|always|never`
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ColorMode {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    pub fn parse(mode: &str) -> Option<ColorMode> {
+        match mode {
+            "auto" => Some(ColorMode::Auto),
+            "always" => Some(ColorMode::Always),
+            "never" => Some(ColorMode::Never),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ColorMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColorMode::Auto => write!(f, "auto"),
+            ColorMode::Always => write!(f, "always"),
+            ColorMode::Never => write!(f, "never"),
+        }
+    }
+}
+
+/// How many colors the output can show.
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug)]
+pub enum ColorSupport {
+    None,
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+/// The xterm defaults of the 16 ANSI colors, in SGR order.
+static ANSI16: [ (Color, (u8, u8, u8)); 16 ] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::White, (229, 229, 229)),
+    (Color::BrightBlack, (127, 127, 127)),
+    (Color::BrightRed, (255, 0, 0)),
+    (Color::BrightGreen, (0, 255, 0)),
+    (Color::BrightYellow, (255, 255, 0)),
+    (Color::BrightBlue, (92, 92, 255)),
+    (Color::BrightMagenta, (255, 0, 255)),
+    (Color::BrightCyan, (0, 255, 255)),
+    (Color::BrightWhite, (255, 255, 255)),
+];
+
+/// The channel levels of the 6x6x6 cube in the 256 colors palette.
+static CUBE_LEVELS: [ u8; 6 ] = [ 0, 95, 135, 175, 215, 255 ];
+
+impl ColorSupport {
+    /// `NO_COLOR` and a stdout that isn't a terminal turn `auto` off,
+    /// `COLORTERM` and `TERM` decide the depth.
+    pub fn detect(mode: ColorMode) -> ColorSupport {
+        match mode {
+            ColorMode::Never => ColorSupport::None,
+            ColorMode::Always => match ColorSupport::from_env() {
+                // forced, so the least we can do is 16 colors
+                ColorSupport::None => ColorSupport::Ansi16,
+                support => support,
+            },
+            ColorMode::Auto => {
+                if env::var_os("NO_COLOR").is_some_and(|no_color| !no_color.is_empty()) || !io::stdout().is_terminal() {
+                    ColorSupport::None
+                } else {
+                    ColorSupport::from_env()
+                }
+            },
+        }
+    }
+    fn from_env() -> ColorSupport {
+        if let Ok(colorterm) = env::var("COLORTERM") {
+            if colorterm == "truecolor" || colorterm == "24bit" {
+                return ColorSupport::TrueColor
+            }
+        }
+        match env::var("TERM") {
+            Ok(term) if term == "dumb" => ColorSupport::None,
+            Ok(term) if term.contains("256color") => ColorSupport::Ansi256,
+            Ok(term) if term.contains("truecolor") || term.contains("direct") => ColorSupport::TrueColor,
+            Ok(_) => ColorSupport::Ansi16,
+            // the Windows console doesn't set `TERM` but supports 24-bit colors
+            Err(_) if cfg!(windows) => ColorSupport::TrueColor,
+            Err(_) => ColorSupport::Ansi16,
+        }
+    }
+    /// The SGR parameters of `color` as a foreground (`background = false`) or background.
+    pub fn sgr(&self, color: Color, background: bool) -> Option<String> {
+        let base = if background { 40 } else { 30 };
+        match (self, color) {
+            (ColorSupport::None, _) => None,
+            (ColorSupport::TrueColor, Color::TrueColor { r, g, b }) => Some(format!("{};2;{};{};{}", base + 8, r, g, b)),
+            (ColorSupport::Ansi256, Color::TrueColor { r, g, b }) => Some(format!("{};5;{}", base + 8, nearest_256((r, g, b)))),
+            (_, Color::TrueColor { r, g, b }) => Some(ansi16_sgr(nearest_16((r, g, b)), base)),
+            (_, color) => Some(ansi16_sgr(color, base)),
+        }
+    }
+}
+
+/// The number of columns of the terminal: `COLUMNS`, then `stty size` on the controlling terminal, then 80.
+pub fn width() -> usize {
+    if let Some(columns) = env::var("COLUMNS").ok().and_then(|columns| columns.parse().ok()).filter(|columns| *columns > 0) {
+        return columns
+    }
+    // stdout may be a pipe, `/dev/tty` is still the terminal the user looks at
+    File::open("/dev/tty").ok()
+        .and_then(|tty| Command::new("stty").arg("size").stdin(tty).stderr(Stdio::null()).output().ok())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .and_then(|size| size.split_whitespace().nth(1)?.parse().ok())
+        .filter(|columns| *columns > 0)
+        .unwrap_or(80)
+}
+
+/// The RGB value of `color`, named colors use the xterm defaults.
+pub fn rgb_of(color: Color) -> (u8, u8, u8) {
+    match color {
+        Color::TrueColor { r, g, b } => (r, g, b),
+        color => ANSI16.iter().find(|(c, _)| *c == color).map(|(_, rgb)| *rgb).unwrap_or((229, 229, 229)),
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+fn ansi16_sgr(color: Color, base: u8) -> String {
+    let index = ANSI16.iter().position(|(c, _)| *c == color).unwrap_or(7) as u8;
+    if index < 8 {
+        format!("{}", base + index)
+    } else {
+        // the bright ones are 90-97 / 100-107
+        format!("{}", base + 60 + index - 8)
+    }
+}
+
+/// Saturated colors only pick from the chromatic entries, a pale red is closer to gray than to red by distance
+/// but should still look red.
+pub fn nearest_16(rgb: (u8, u8, u8)) -> Color {
+    let chroma = |(r, g, b): (u8, u8, u8)| r.max(g).max(b) - r.min(g).min(b);
+    let saturated = chroma(rgb) > 60;
+    ANSI16.iter()
+        .filter(|(_, ansi)| !saturated || chroma(*ansi) > 0)
+        .min_by_key(|(_, ansi)| distance(rgb, *ansi))
+        .map(|(color, _)| *color)
+        .unwrap()
+}
+
+/// Picks the nearest of the 6x6x6 cube (16-231) and the gray ramp (232-255).
+pub fn nearest_256(rgb: (u8, u8, u8)) -> u8 {
+    let level = |c: u8| {
+        (0..6).min_by_key(|i| (CUBE_LEVELS[*i] as i32 - c as i32).abs()).unwrap()
+    };
+    let (r, g, b) = (level(rgb.0), level(rgb.1), level(rgb.2));
+    let cube = (CUBE_LEVELS[r], CUBE_LEVELS[g], CUBE_LEVELS[b]);
+    let average = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let gray_index = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray_level = 8 + 10 * gray_index;
+    if distance(rgb, (gray_level, gray_level, gray_level)) < distance(rgb, cube) {
+        232 + gray_index
+    } else {
+        16 + 36 * r as u8 + 6 * g as u8 + b as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use colored::Color;
+    use super::{nearest_16, nearest_256, ColorSupport};
+
+    #[test]
+    fn it_should_quantize_to_the_nearest_palette_color() {
+        assert_eq!(nearest_256((255, 0, 0)), 196);
+        assert_eq!(nearest_256((255, 107, 107)), 203);
+        assert_eq!(nearest_256((155, 155, 155)), 247);
+        assert_eq!(nearest_256((0, 0, 0)), 16);
+        assert_eq!(nearest_16((107, 255, 107)), Color::BrightGreen);
+        assert_eq!(nearest_16((255, 107, 107)), Color::BrightRed);
+        assert_eq!(nearest_16((155, 155, 155)), Color::BrightBlack);
+        assert_eq!(ColorSupport::Ansi16.sgr(Color::TrueColor { r: 150, g: 150, b: 150 }, true), Some(String::from("100")));
+        assert_eq!(ColorSupport::None.sgr(Color::Red, false), None);
+    }
+}
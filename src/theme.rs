@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+use std::fs;
+use serde_json::Value;
+use crate::config::merge_color_map;
+use crate::logger::Logger;
+use crate::style::Style;
+use crate::theme_import;
+
+/// A palette, either named from `themes/{name}.json` or imported from an editor theme file.
+pub struct Theme {
+    pub color_map: HashMap<String, Style>,
+}
+
+impl Theme {
+    /// Whether `name` is a path to a theme file rather than a name under `themes`.
+    pub fn is_file(name: &str) -> bool {
+        name.ends_with(".json") || name.ends_with(".tmTheme")
+    }
+    /// `name` is a theme under `themes`, or a path to a `.json` / `.tmTheme` file.
+    /// VS Code (`tokenColors`) and TextMate themes are imported by their scopes.
+    pub fn load(root: &String, name: &String, logger: &mut Logger) -> Result<Theme, String> {
+        if !Theme::is_file(name) {
+            let path = format!("{}/themes/{}.json", root, name);
+            let json = fs::read_to_string(&path)
+                .map_err(|_| format!("Unknown theme `{}`: there is no `themes/{}.json`.", name, name))?;
+            return Theme::from_json(&path, &json, logger)
+        }
+        let source = fs::read_to_string(name)
+            .map_err(|e| format!("Theme IO Error: {}: {}", name, e))?;
+        if name.ends_with(".json") && !source.contains("\"tokenColors\"") {
+            return Theme::from_json(name, &source, logger)
+        }
+        Ok(Theme { color_map: theme_import::import(name, &source, logger)? })
+    }
+    fn from_json(path: &String, json: &str, logger: &mut Logger) -> Result<Theme, String> {
+        let object = match serde_json::from_str::<Value>(json) {
+            Ok(Value::Object(object)) => object,
+            Ok(_) => return Err(format!("Theme Error: `{}` should be a JSON object.", path)),
+            Err(e) => return Err(format!("Theme Error: `{}`: {}", path, e)),
+        };
+        let mut color_map = Vec::new();
+        for (key, value) in &object {
+            match key.as_str() {
+                "color_map" => merge_color_map(&mut color_map, path, value, logger),
+                _ => {
+                    logger.warn(format!("Unknown key `{}` in `{}`.", key, path));
+                },
+            }
+        }
+        Ok(Theme { color_map: color_map.into_iter().collect() })
+    }
+}
@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::Chars;
+use serde_json::Value;
+use crate::config::{Config, parse_rgb};
+use crate::logger::{Logger, NoteFor};
+use crate::plist::PlistParser;
+use crate::style::{Style, parse_color};
+
+/// The TextMate scopes each color name is looked up by, the first one covered by the theme wins.
+/// The ones without scopes come from the editor colors of the theme.
+static SCOPE_MAP: [ (&str, &[&str]); 20 ] = [
+    ("title", &["markup.heading", "entity.name.section", "keyword"]),
+    ("file_path", &["markup.underline.link", "string.other.link", "entity.name.function"]),
+    ("keyword", &["keyword.control", "keyword", "storage.modifier"]),
+    ("literal_string", &["string.quoted", "string"]),
+    ("literal_number", &["constant.numeric"]),
+    ("literal_boolean", &["constant.language.boolean", "constant.language"]),
+    ("type", &["entity.name.type", "support.type", "storage.type"]),
+    ("nextline", &[]),
+    ("note", &["comment.line", "comment"]),
+    ("bold", &["entity.name.function", "support.function"]),
+    ("symbol", &["variable.other", "variable"]),
+    ("background", &[]),
+    ("whitespace", &[]),
+    ("highlight_line", &[]),
+    ("trailing_whitespace", &["invalid.illegal", "invalid"]),
+    ("invalid", &["invalid.illegal", "invalid"]),
+    ("hex_null", &["comment"]),
+    ("hex_printable", &["string.quoted", "string"]),
+    ("hex_whitespace", &["constant.character.escape", "constant.character"]),
+    ("hex_control", &["constant.numeric"]),
+];
+
+/// One `tokenColors` (VS Code) or `settings` (TextMate) entry.
+struct ScopeRule {
+    selectors: Vec<String>,
+    style: Style,
+}
+
+/// Reads a VS Code theme (`.json` with `tokenColors`) or a TextMate theme (`.tmTheme`).
+/// Only the color names covered by the theme are returned.
+pub fn import(path: &String, source: &str, logger: &mut Logger) -> Result<HashMap<String, Style>, String> {
+    let (rules, editor) = if path.ends_with(".tmTheme") {
+        let plist = PlistParser::new(source).parse().map_err(|e| format!("Theme Error: `{}`: {}", path, e))?;
+        tm_theme_rules(&plist)
+    } else {
+        let json = serde_json::from_str::<Value>(&strip_jsonc(source))
+            .map_err(|e| format!("Theme Error: `{}`: {}", path, e))?;
+        if json.get("include").is_some() {
+            logger.warn(format!("`include` in `{}` is not supported, only its own `tokenColors` are imported.", path));
+        }
+        vscode_rules(&json)
+    };
+    let mut color_map = HashMap::new();
+    for (name, scopes) in SCOPE_MAP.iter() {
+        let color = if scopes.is_empty() {
+            editor.get(name).cloned()
+        } else {
+            scopes.iter().find_map(|scope| lookup(&rules, scope))
+        };
+        if let Some(color) = color {
+            color_map.insert(name.to_string(), color);
+        }
+    }
+    let mut missing: Vec<String> = Config::default().color_map.into_keys()
+        .filter(|name| !color_map.contains_key(name))
+        .collect();
+    if !missing.is_empty() {
+        missing.sort();
+        logger.warn(format!("Theme `{}` doesn't cover {} color(s), the defaults are used for them.", path, missing.len()));
+        logger.note(format!("Missing: {:?}", missing), NoteFor::Warn);
+    }
+    Ok(color_map)
+}
+
+/// Finds the most specific rule whose selector is a prefix of `scope`, later rules win a tie.
+fn lookup(rules: &[ScopeRule], scope: &str) -> Option<Style> {
+    let mut best: Option<(usize, Style)> = None;
+    for rule in rules {
+        for selector in &rule.selectors {
+            let matched = scope == selector
+                || (scope.starts_with(selector.as_str()) && scope[selector.len()..].starts_with('.'));
+            if matched && best.is_none_or(|(len, _)| selector.len() >= len) {
+                best = Some((selector.len(), rule.style));
+            }
+        }
+    }
+    best.map(|(_, color)| color)
+}
+
+/// Splits `"a, b"` or `["a", "b"]` into plain selectors.
+/// Descendant (`a b`) and exclusion (`a - b`) selectors need a context we don't have, so they are skipped.
+fn selectors(scope: &Value) -> Vec<String> {
+    let raw: Vec<&str> = match scope {
+        Value::String(scope) => scope.split(',').collect(),
+        Value::Array(scopes) => scopes.iter().filter_map(|scope| scope.as_str()).flat_map(|scope| scope.split(',')).collect(),
+        _ => Vec::new(),
+    };
+    raw.iter()
+        .map(|selector| selector.trim())
+        .filter(|selector| !selector.is_empty() && !selector.contains(' '))
+        .map(|selector| selector.to_string())
+        .collect()
+}
+
+/// `fontStyle` is a space separated list such as `"bold italic"`.
+fn rule(entry: &Value) -> Option<ScopeRule> {
+    let settings = entry.get("settings")?;
+    let mut style = Style {
+        foreground: settings.get("foreground").and_then(parse_color),
+        background: settings.get("background").and_then(parse_color),
+        ..Style::default()
+    };
+    let font_style = settings.get("fontStyle").and_then(|font_style| font_style.as_str()).unwrap_or("");
+    for attr in font_style.split_whitespace() {
+        match attr {
+            "bold" => style.bold = true,
+            "italic" => style.italic = true,
+            "underline" => style.underline = true,
+            "strikethrough" => style.strikethrough = true,
+            _ => {},
+        }
+    }
+    if style == Style::default() {
+        return None
+    }
+    Some(ScopeRule {
+        selectors: selectors(entry.get("scope")?),
+        style,
+    })
+}
+
+/// `nextline` (the gutter), `whitespace` (the invisibles), `highlight_line` (the current line)
+/// and `background` (the page) from the editor colors.
+fn editor_styles(gutter: Option<&Value>, invisibles: Option<&Value>, line_highlight: Option<&Value>, foreground: Option<&Value>, background: Option<&Value>) -> HashMap<&'static str, Style> {
+    let mut editor = HashMap::new();
+    if let Some(gutter) = gutter.and_then(parse_rgb) {
+        editor.insert("nextline", Style::foreground(gutter));
+    }
+    if let Some(invisibles) = invisibles.and_then(parse_rgb) {
+        editor.insert("whitespace", Style::foreground(invisibles));
+    }
+    if let Some(line_highlight) = line_highlight.and_then(parse_color) {
+        editor.insert("highlight_line", Style { background: Some(line_highlight), bold: true, ..Style::default() });
+    }
+    let page = Style {
+        foreground: foreground.and_then(parse_color),
+        background: background.and_then(parse_color),
+        ..Style::default()
+    };
+    if page != Style::default() {
+        editor.insert("background", page);
+    }
+    editor
+}
+
+fn vscode_rules(json: &Value) -> (Vec<ScopeRule>, HashMap<&'static str, Style>) {
+    let rules = json.get("tokenColors")
+        .and_then(|token_colors| token_colors.as_array())
+        .map(|token_colors| token_colors.iter().filter_map(rule).collect())
+        .unwrap_or_default();
+    let colors = json.get("colors");
+    let color = |name: &str| colors.and_then(|colors| colors.get(name));
+    (rules, editor_styles(color("editorLineNumber.foreground"), color("editorWhitespace.foreground"), color("editor.lineHighlightBackground"), color("editor.foreground"), color("editor.background")))
+}
+
+fn tm_theme_rules(plist: &Value) -> (Vec<ScopeRule>, HashMap<&'static str, Style>) {
+    let entries = plist.get("settings").and_then(|settings| settings.as_array());
+    let rules = entries
+        .map(|entries| entries.iter().filter_map(rule).collect())
+        .unwrap_or_default();
+    // the entry without a scope holds the editor colors
+    let settings = entries
+        .and_then(|entries| entries.iter().find(|entry| entry.get("scope").is_none()))
+        .and_then(|entry| entry.get("settings"));
+    let color = |name: &str| settings.and_then(|settings| settings.get(name));
+    (rules, editor_styles(color("gutterForeground"), color("invisibles"), color("lineHighlight"), color("foreground"), color("background")))
+}
+
+/// VS Code themes are JSONC: drops comments and trailing commas so that serde_json can read them.
+pub fn strip_jsonc(source: &str) -> String {
+    let mut stripped = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => {
+                stripped.push(ch);
+                skip_string(&mut chars, &mut stripped);
+            },
+            '/' if chars.peek() == Some(&'/') => {
+                for ch in chars.by_ref() {
+                    if ch == '\n' {
+                        stripped.push(ch);
+                        break
+                    }
+                }
+            },
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                for ch in chars.by_ref() {
+                    if last == '*' && ch == '/' {
+                        break
+                    }
+                    last = ch;
+                }
+            },
+            _ => stripped.push(ch),
+        }
+    }
+    let mut res = String::with_capacity(stripped.len());
+    let mut chars = stripped.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => {
+                res.push(ch);
+                skip_string(&mut chars, &mut res);
+            },
+            ',' => {
+                // a comma followed only by blanks and a closing bracket is a trailing comma
+                let next = chars.clone().find(|ch| !ch.is_whitespace());
+                if !matches!(next, Some('}') | Some(']')) {
+                    res.push(ch);
+                }
+            },
+            _ => res.push(ch),
+        }
+    }
+    res
+}
+
+/// Copies the rest of a string literal, the opening `"` is already consumed.
+fn skip_string(chars: &mut Peekable<Chars>, res: &mut String) {
+    while let Some(ch) = chars.next() {
+        res.push(ch);
+        if ch == '\\' {
+            if let Some(escaped) = chars.next() {
+                res.push(escaped);
+            }
+        } else if ch == '"' {
+            break
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::style::Style;
+    use super::{lookup, rule, strip_jsonc};
+
+    #[test]
+    fn it_should_read_jsonc_and_pick_the_most_specific_scope() {
+        let json = strip_jsonc(r##"{
+            // comment
+            "tokenColors": [
+                { "scope": "keyword, string", "settings": { "foreground": "#111111" } },
+                /* "scope": "keyword.control" */
+                { "scope": ["keyword.control"], "settings": { "foreground": "#222", }, },
+            ],
+        }"##);
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let rules: Vec<_> = json["tokenColors"].as_array().unwrap().iter().filter_map(rule).collect();
+        assert_eq!(lookup(&rules, "keyword.control.rust"), Some(Style::foreground((0x22, 0x22, 0x22))));
+        assert_eq!(lookup(&rules, "keyword.operator"), Some(Style::foreground((0x11, 0x11, 0x11))));
+        assert_eq!(lookup(&rules, "keywords"), None);
+        assert_eq!(rule(&json!({ "settings": {} })).map(|rule| rule.selectors.len()), None);
+        let bold = rule(&json!({ "scope": "entity.name.function", "settings": { "fontStyle": "bold italic" } })).unwrap();
+        assert!(bold.style.bold && bold.style.italic && bold.style.foreground.is_none());
+    }
+}
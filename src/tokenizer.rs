@@ -1,64 +1,52 @@
 use std::rc::Rc;
 
-use crate::{app::Token, logger::{Logger, NoteFor}};
-
-static OPERATORS: [ u8; 30 ] = [
-    b'+', 
-    b'-', 
-    b'*', 
-    b'/', 
-    b'~', 
-    b'`', 
-    b'!', 
-    b'@', 
-    b'#', 
-    b'$', 
-    b'%', 
-    b'^', 
-    b'&', 
-    b'*', 
-    b'(', 
-    b')', 
-    b'=', 
-    b'[', 
-    b']', 
-    b'{', 
-    b'}', 
-    b'|', 
-    b';', 
-    b':', 
-    b'?', 
-    b'.', 
-    b',', 
-    b'<', 
-    b'>', 
-    b'\\', 
-];
-
-static BLANKS: [ u8; 3 ] = [
-    b' ',
-    b'\t',
-    b'\r',
-];
+use crate::{app::Token, language_pattern::CharClasses, logger::{Logger, NoteFor}};
 
 static NEXTLINE: [ u8; 1 ] = [
     b'\n',
 ];
 
-static STRING_SYMBOL: [ u8; 2 ] = [
-    b'\'',
-    b'"',
-];
+/// A Latin-1 file has an invalid sequence on every other line, only the first ones are warnings.
+static MAX_INVALID_LOGS: usize = 5;
 
 pub struct Tokenizer<'a> {
     logger: &'a mut Logger,
     bytes: Vec<u8>,
     curr: isize,
+    /// `CharClasses` of the grammar, without the word characters.
+    operators: Vec<u8>,
+    blanks: Vec<u8>,
+    quotes: Vec<u8>,
+    /// `CharClasses::operator_table`, the longest first.
+    operator_table: Vec<Vec<u8>>,
+    /// The invalid UTF-8 sequences so far.
+    invalid: usize,
+    /// The 1-based line and the characters before the next token on it, for the diagnostics.
+    line: usize,
+    column: usize,
 }
 
 impl<'a> Tokenizer<'a> {
-    pub fn new(logger: &'a mut Logger, bytes: Vec<u8>) -> Tokenizer {
-        Tokenizer { logger, bytes, curr: -1 }
+    pub fn new(logger: &'a mut Logger, bytes: Vec<u8>, classes: &CharClasses) -> Tokenizer<'a> {
+        let set = |chars: &String| chars.bytes().filter(|byte| !classes.word.as_bytes().contains(byte)).collect();
+        let operators: Vec<u8> = set(&classes.operator);
+        let mut operator_table: Vec<Vec<u8>> = classes.operator_table.iter()
+            .map(|operator| operator.as_bytes().to_vec())
+            .filter(|operator| !operator.is_empty() && operator.iter().all(|byte| operators.contains(byte)))
+            .collect();
+        operator_table.sort_by_key(|operator| std::cmp::Reverse(operator.len()));
+        Tokenizer {
+            logger,
+            bytes,
+            curr: -1,
+            operators,
+            blanks: set(&classes.blank),
+            quotes: set(&classes.quote),
+            operator_table,
+            invalid: 0,
+            line: 1,
+            column: 0,
+        }
     }
     #[inline]
     fn has_next(&self) -> bool {
@@ -77,73 +65,138 @@ impl<'a> Tokenizer<'a> {
     fn forward(&mut self) {
         self.curr += 1;
     }
+    /// Turns the bytes from `begin` to `end` (inclusive) into tokens without losing any of them:
+    /// the valid UTF-8 becomes one token, every invalid sequence an `invalid` token that reads `\xNN`.
+    fn push_run(&mut self, tokens: &mut Vec<Token>, begin: usize, end: usize) {
+        let mut start = begin;
+        while start <= end {
+            let run = &self.bytes[start..=end];
+            let (valid, invalid) = match std::str::from_utf8(run) {
+                Ok(_) => (run.len(), 0),
+                // `None` is a sequence cut short by the end of the run
+                Err(e) => (e.valid_up_to(), e.error_len().unwrap_or(run.len() - e.valid_up_to())),
+            };
+            if valid > 0 {
+                let token_string = String::from_utf8(run[..valid].to_vec()).unwrap();
+                self.column += token_string.chars().count();
+                tokens.push(Token::new(token_string, (start, start + valid - 1)));
+            }
+            if invalid > 0 {
+                let from = start + valid;
+                let token_string: String = self.bytes[from..from + invalid].iter().map(|byte| format!("\\x{:02X}", byte)).collect();
+                self.invalid += 1;
+                self.column += 1;
+                let msg = format!("Invalid UTF-8 `{}` at line {}, column {}, it is printed as is.", token_string, self.line, self.column);
+                if self.invalid <= MAX_INVALID_LOGS {
+                    self.logger.warn(msg);
+                } else {
+                    self.logger.info(msg);
+                }
+                let mut token = Token::new(token_string, (from, from + invalid - 1));
+                let color = Rc::new(String::from("invalid"));
+                token.color(color.clone(), color);
+                tokens.push(token);
+            }
+            start += valid + invalid;
+        }
+    }
     // the normal tokenizer
     pub fn tokenize(&mut self) -> (Vec<Token>, usize) {
         let mut tokens = Vec::new();
-        let mut line = 1;
         while self.has_next() {
             let curr = self.next();
-            if OPERATORS.contains(&curr) {
+            if self.operators.contains(&curr) {
                 let begin = self.curr as usize;
-                while self.has_next() && OPERATORS.contains(&self.peek()) {
-                    self.forward();
-                }
-                let vec: Vec<u8> = self.bytes[begin..=(self.curr as usize)].iter().cloned().collect();
-                match String::from_utf8(vec) {
-                    Ok(token_string) => {
-                        let token = Token::new(token_string, (begin, self.curr as usize));
-                        tokens.push(token);
-                    },
-                    Err(e) => {
-                        self.logger.error(format!("UTF8 Err: Unexpected UTF8 Char."));
-                        self.logger.info(format!("Raw Err: {}", e));
-                    }
-                }
-            } else if BLANKS.contains(&curr) {
+                // `);` is two tokens, `..=` one
+                let len = self.operator_table.iter()
+                    .find(|operator| self.bytes[begin..].starts_with(operator))
+                    .map_or(1, |operator| operator.len());
+                self.curr += len as isize - 1;
+                self.push_run(&mut tokens, begin, self.curr as usize);
+            } else if self.blanks.contains(&curr) {
                 let begin = self.curr as usize;
-                while self.has_next() && BLANKS.contains(&self.peek()) {
+                while self.has_next() && self.blanks.contains(&self.peek()) {
                     self.forward();
                 }
-                let vec: Vec<u8> = self.bytes[begin..=(self.curr as usize)].iter().cloned().collect();
-                match String::from_utf8(vec) {
-                    Ok(token_string) => {
-                        let token = Token::new(token_string, (begin, self.curr as usize));
-                        tokens.push(token);
-                    },
-                    Err(e) => {
-                        self.logger.error(format!("UTF8 Err: Unexpected UTF8 Char."));
-                        self.logger.note(format!("Raw Err: {}", e), NoteFor::Error);
-                    }
-                }
+                self.push_run(&mut tokens, begin, self.curr as usize);
             } else if NEXTLINE.contains(&curr) {
                 let begin = self.curr as usize;
                 let mut token = Token::new(String::from("\n"), (begin, self.curr as usize));
-                token.color(Rc::new(String::from("nextline")));
+                let nextline = Rc::new(String::from("nextline"));
+                token.color(nextline.clone(), nextline);
                 tokens.push(token);
-                line += 1;
-            } else if STRING_SYMBOL.contains(&curr) {
+                self.line += 1;
+                self.column = 0;
+            } else if self.quotes.contains(&curr) {
                 let begin = self.curr as usize;
-                let token = Token::new(String::from_utf8(vec! [curr]).unwrap(), (begin, self.curr as usize));
-                tokens.push(token);
+                self.push_run(&mut tokens, begin, begin);
             } else {
                 let begin = self.curr as usize;
-                while self.has_next() && (!OPERATORS.contains(&self.peek()) && !BLANKS.contains(&self.peek())) {
+                while self.has_next() && (!self.operators.contains(&self.peek()) && !self.blanks.contains(&self.peek()) && !NEXTLINE.contains(&self.peek())) {
                     self.forward();
                 }
-                let vec: Vec<u8> = self.bytes[begin..=(self.curr as usize)].iter().cloned().collect();
-                match String::from_utf8(vec) {
-                    Ok(token_string) => {
-                        let token = Token::new(token_string, (begin, self.curr as usize));
-                        tokens.push(token);
-                    },
-                    Err(e) => {
-                        self.logger.error(format!("UTF8 Err: Unexpected UTF8 char."));
-                        self.logger.note(format!("Raw Err: {}", e), NoteFor::Error);
-                    }
-                }
+                self.push_run(&mut tokens, begin, self.curr as usize);
             }
             //self.forward();
         }
-        (tokens, line)
+        if self.invalid > MAX_INVALID_LOGS {
+            self.logger.note(format!("{} more invalid UTF-8 sequences are logged as info.", self.invalid - MAX_INVALID_LOGS), NoteFor::Warn);
+        }
+        (tokens, self.line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::LogLevel;
+    use crate::logger::Logger;
+    use crate::language_pattern::CharClasses;
+    use super::Tokenizer;
+
+    #[test]
+    fn it_should_end_a_word_at_the_newline() {
+        let mut logger = Logger::new(LogLevel::Never);
+        let (tokens, lines) = Tokenizer::new(&mut logger, b"a b\nc\n".to_vec(), &CharClasses::default()).tokenize();
+        let texts: Vec<&str> = tokens.iter().map(|token| token.as_str()).collect();
+        assert_eq!(texts, vec![ "a", " ", "b", "\n", "c", "\n" ]);
+        assert_eq!(lines, 3);
+        assert_eq!(tokens[4].range(), (4, 4));
+    }
+
+    #[test]
+    fn it_should_keep_every_byte() {
+        let mut logger = Logger::new(LogLevel::All);
+        let bytes = b"caf\xe9 = \"\xc3\xa9t\xc3\xa9\";\n\xff\xfe x\n\xe2\x82".to_vec();
+        let (tokens, _) = Tokenizer::new(&mut logger, bytes.clone(), &CharClasses::default()).tokenize();
+        let mut joined = Vec::new();
+        for token in &tokens {
+            let (begin, end) = token.range();
+            assert_eq!(begin, joined.len());
+            joined.extend_from_slice(&bytes[begin..=end]);
+        }
+        assert_eq!(joined, bytes);
+        let invalid: Vec<&str> = tokens.iter().filter(|token| token.color_name() == "invalid").map(|token| token.as_str()).collect();
+        assert_eq!(invalid, vec![ "\\xE9", "\\xFF", "\\xFE", "\\xE2\\x82" ]);
+        assert_eq!(logger.len(), 4);
+        let logs = logger.to_string();
+        assert!(logs.contains("`\\xE9` at line 1, column 4") && logs.contains("`\\xFE` at line 2, column 2") && logs.contains("`\\xE2\\x82` at line 3, column 1"));
+    }
+
+    #[test]
+    fn it_should_split_by_the_classes_of_the_grammar() {
+        let mut logger = Logger::new(LogLevel::Never);
+        let classes = CharClasses { word: String::from("-$"), ..CharClasses::default() };
+        let (tokens, _) = Tokenizer::new(&mut logger, b"echo $my-var;".to_vec(), &classes).tokenize();
+        let texts: Vec<&str> = tokens.iter().map(|token| token.as_str()).collect();
+        assert_eq!(texts, vec![ "echo", " ", "$my-var", ";" ]);
+    }
+
+    #[test]
+    fn it_should_split_operators_by_the_longest_match() {
+        let mut logger = Logger::new(LogLevel::Never);
+        let classes = CharClasses { operator_table: vec![ String::from("::"), String::from(".."), String::from("..="), String::from("->"), String::from("$x") ], ..CharClasses::default() };
+        let (tokens, _) = Tokenizer::new(&mut logger, b"a::b(0..=9)?;->...$x".to_vec(), &classes).tokenize();
+        let texts: Vec<&str> = tokens.iter().map(|token| token.as_str()).collect();
+        assert_eq!(texts, vec![ "a", "::", "b", "(", "0", "..=", "9", ")", "?", ";", "->", "..", ".", "$", "x" ]);
     }
 }